@@ -1,68 +1,198 @@
 use crate::utils;
 
 use std::collections::HashMap;
+use std::fmt;
+
+/// Errors surfaced while pairing up the two location lists.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ListError {
+    /// The lists must pair up one-to-one.
+    LengthMismatch { left: usize, right: usize },
+    /// An intermediate value did not fit into `i64`.
+    Overflow,
+}
+
+impl fmt::Display for ListError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ListError::LengthMismatch { left, right } => {
+                write!(f, "list length mismatch: left has {}, right has {}", left, right)
+            }
+            ListError::Overflow => write!(f, "arithmetic overflow while summing"),
+        }
+    }
+}
+
+impl std::error::Error for ListError {}
 
 pub fn solve(day: u32) {
     println!("Started Day{}!",day );
+    let show_stats = utils::has_flag("--stats");
     if let Ok(contents) =  utils::read_file( &format!("src/solutions/day{}/input.txt", day)) {
-        if let Ok((mut left_vals, mut right_vals)) = process_content(&contents) {
-            left_vals.sort();
-            right_vals.sort();
-            part_1(&mut left_vals, &mut right_vals);
-            part_2(&mut left_vals, &mut right_vals);
+        match process_content(&contents) {
+            Ok((mut left_vals, mut right_vals)) => {
+                left_vals.sort();
+                right_vals.sort();
+                part_1(&left_vals, &right_vals);
+                part_2(&left_vals, &right_vals);
+                if show_stats {
+                    match compute_stats(&left_vals, &right_vals) {
+                        Ok(stats) => print_stats(&stats),
+                        Err(e) => println!("Stats: {}", e),
+                    }
+                }
+            }
+            Err(e) => println!("Failed to parse input: {}", e),
         }
     }
 }
 
-fn part_1(left_vals: &mut Vec<i32>, right_vals: &mut Vec<i32>) {
-    let diff_sum = calculate_differences(&left_vals, &right_vals);
-    println!("Part1: Sum of differences: {}", diff_sum);
+fn part_1(left_vals: &[i64], right_vals: &[i64]) {
+    match calculate_differences(left_vals, right_vals) {
+        Ok(diff_sum) => println!("Part1: Sum of differences: {}", diff_sum),
+        Err(e) => println!("Part1: {}", e),
+    }
 }
 
-fn part_2(left_vals: &mut Vec<i32>, right_vals: &mut Vec<i32>) {
-    let freq_sum = calculate_freq(&left_vals, &right_vals);
-    println!("Part2: Sum of differences: {}", freq_sum);
+fn part_2(left_vals: &[i64], right_vals: &[i64]) {
+    match calculate_freq(left_vals, right_vals) {
+        Ok(freq_sum) => println!("Part2: Sum of differences: {}", freq_sum),
+        Err(e) => println!("Part2: {}", e),
+    }
 }
 
-fn calculate_freq(arr1: &Vec<i32>, arr2: &Vec<i32>) -> i32 {
-    let mut ans = 0;
-    let mut map = HashMap::new();
-    for &num in arr2 {
-        *map.entry(num).or_insert(0) += 1;
+fn check_lengths(arr1: &[i64], arr2: &[i64]) -> Result<(), ListError> {
+    if arr1.len() != arr2.len() {
+        return Err(ListError::LengthMismatch { left: arr1.len(), right: arr2.len() });
     }
+    Ok(())
+}
+
+/// Similarity score: each left value multiplied by how often it appears on the right.
+fn calculate_freq(arr1: &[i64], arr2: &[i64]) -> Result<i64, ListError> {
+    check_lengths(arr1, arr2)?;
+    let map = count_occurrences(arr2);
+    let mut ans: i64 = 0;
     for &val in arr1 {
         if let Some(&freq) = map.get(&val) {
-            ans += &val * freq;
+            let score = val.checked_mul(freq).ok_or(ListError::Overflow)?;
+            ans = ans.checked_add(score).ok_or(ListError::Overflow)?;
         }
     }
-    ans
+    Ok(ans)
+}
+
+/// Total distance between the sorted lists, paired index by index.
+fn calculate_differences(arr1: &[i64], arr2: &[i64]) -> Result<i64, ListError> {
+    check_lengths(arr1, arr2)?;
+    let mut sum: i64 = 0;
+    for diff in pairwise_differences(arr1, arr2)? {
+        sum = sum.checked_add(diff).ok_or(ListError::Overflow)?;
+    }
+    Ok(sum)
+}
+
+fn pairwise_differences(arr1: &[i64], arr2: &[i64]) -> Result<Vec<i64>, ListError> {
+    arr1.iter()
+        .zip(arr2)
+        .map(|(&a, &b)| {
+            a.checked_sub(b)
+                .and_then(i64::checked_abs)
+                .ok_or(ListError::Overflow)
+        })
+        .collect()
+}
+
+fn count_occurrences(values: &[i64]) -> HashMap<i64, i64> {
+    let mut map = HashMap::new();
+    for &num in values {
+        *map.entry(num).or_insert(0) += 1;
+    }
+    map
 }
 
-fn calculate_differences(arr1: &Vec<i32>, arr2: &Vec<i32>) -> i32 {
-    let mut sum = 0;
-    
-    // Make sure arrays are of same length
-    if arr1.len() == arr2.len() {
-        for i in 0..arr1.len() {
-            // Calculate absolute difference
-            let diff = (arr1[i] - arr2[i]).abs();
-            sum += diff;
+/// Summary of the pairwise differences and the values both lists share.
+#[derive(Debug)]
+pub struct ListStats {
+    pub pairs: usize,
+    pub median_difference: f64,
+    pub mean_difference: f64,
+    /// `(lower bound, upper bound, count)` per bucket, bounds inclusive.
+    pub histogram: Vec<(i64, i64, usize)>,
+    /// `(value, left count, right count)`, most frequent first.
+    pub top_shared: Vec<(i64, i64, i64)>,
+}
+
+const HISTOGRAM_BUCKETS: i64 = 10;
+const TOP_SHARED: usize = 5;
+
+fn compute_stats(arr1: &[i64], arr2: &[i64]) -> Result<ListStats, ListError> {
+    check_lengths(arr1, arr2)?;
+    let mut diffs = pairwise_differences(arr1, arr2)?;
+    diffs.sort_unstable();
+
+    let pairs = diffs.len();
+    let total: i128 = diffs.iter().map(|&d| d as i128).sum();
+    let mean_difference = if pairs == 0 { 0.0 } else { total as f64 / pairs as f64 };
+    let median_difference = match pairs {
+        0 => 0.0,
+        n if n % 2 == 1 => diffs[n / 2] as f64,
+        n => (diffs[n / 2 - 1] as f64 + diffs[n / 2] as f64) / 2.0,
+    };
+
+    let mut histogram = Vec::new();
+    if let (Some(&min), Some(&max)) = (diffs.first(), diffs.last()) {
+        let width = ((max - min) / HISTOGRAM_BUCKETS + 1).max(1);
+        let mut lower = min;
+        while lower <= max {
+            let upper = lower.saturating_add(width - 1);
+            let count = diffs.iter().filter(|&&d| d >= lower && d <= upper).count();
+            histogram.push((lower, upper, count));
+            match upper.checked_add(1) {
+                Some(next) => lower = next,
+                None => break,
+            }
         }
     }
-    
-    sum
+
+    let left_counts = count_occurrences(arr1);
+    let right_counts = count_occurrences(arr2);
+    let mut top_shared: Vec<(i64, i64, i64)> = left_counts
+        .iter()
+        .filter_map(|(&val, &left)| right_counts.get(&val).map(|&right| (val, left, right)))
+        .collect();
+    top_shared.sort_by(|a, b| (b.1 * b.2).cmp(&(a.1 * a.2)).then(a.0.cmp(&b.0)));
+    top_shared.truncate(TOP_SHARED);
+
+    Ok(ListStats { pairs, median_difference, mean_difference, histogram, top_shared })
 }
 
-fn process_content(contents: &str) -> Result<(Vec<i32>, Vec<i32>), &'static str> {
+fn print_stats(stats: &ListStats) {
+    println!("Stats: {} pairs", stats.pairs);
+    println!("  median difference: {}", stats.median_difference);
+    println!("  mean difference:   {:.2}", stats.mean_difference);
+    println!("  histogram of differences:");
+    let widest = stats.histogram.iter().map(|&(_, _, c)| c).max().unwrap_or(0).max(1);
+    for &(lower, upper, count) in &stats.histogram {
+        let bar = "#".repeat(count * 40 / widest);
+        println!("    {:>8}..={:<8} {:>5} {}", lower, upper, count, bar);
+    }
+    println!("  most frequent shared values:");
+    for &(val, left, right) in &stats.top_shared {
+        println!("    {} (left x{}, right x{})", val, left, right);
+    }
+}
+
+fn process_content(contents: &str) -> Result<(Vec<i64>, Vec<i64>), &'static str> {
     let mut left_vals = Vec::new();
     let mut right_vals = Vec::new();
 
     for line in contents.lines() {
-        let numbers: Vec<i32> = line
+        let numbers: Vec<i64> = line
             .split_whitespace()
             .filter_map(|s| s.parse().ok())
             .collect();
-        
+
         if numbers.len() == 2 {
             left_vals.push(numbers[0]);
             right_vals.push(numbers[1]);
@@ -72,4 +202,4 @@ fn process_content(contents: &str) -> Result<(Vec<i32>, Vec<i32>), &'static str>
     }
 
     Ok((left_vals, right_vals))
-}
\ No newline at end of file
+}
@@ -1,10 +1,30 @@
 use crate::utils;
 
+use std::fmt;
+
 pub fn solve(day: u32) {
     println!("Started Day{}!",day );
-    
+
+    let tolerance = match utils::flag_value("--tolerance").map(|s| s.parse::<usize>().map_err(|_| s)) {
+        Some(Ok(k)) => k,
+        Some(Err(value)) => {
+            println!("invalid --tolerance '{}' (expects a non-negative integer)", value);
+            return;
+        }
+        None => 1,
+    };
+    let show_removals = utils::has_flag("--removals");
+    let show_diagnostics = utils::has_flag("--diagnose");
+    let rules = match SafetyRules::from_args() {
+        Ok(rules) => rules,
+        Err(e) => {
+            println!("{}", e);
+            return;
+        }
+    };
+
     if let Ok(contents) = utils::read_file( &format!("src/solutions/day{}/input.txt", day)) {
-        
+
         // Parse all lines once
         let all_numbers: Vec<Vec<i32>> = contents
             .lines()
@@ -14,61 +34,234 @@ pub fn solve(day: u32) {
                     .collect()
             })
             .collect();
-        
+
         // Use the parsed numbers for both counts
         let safe_count = all_numbers
             .iter()
-            .filter(|numbers| is_safe_without_removal(numbers))
-            .count();
-            
-        let with_removal_count = all_numbers
-            .iter()
-            .filter(|numbers| is_safe_sequence(numbers))
+            .filter(|numbers| is_safe_without_removal(numbers, &rules))
             .count();
+
+        if show_diagnostics {
+            for (line, numbers) in all_numbers.iter().enumerate() {
+                if let Some(violation) = diagnose(numbers, &rules) {
+                    println!(
+                        "Report {}: levels {} -> {} at index {}: {}",
+                        line + 1, violation.window.0, violation.window.1, violation.index, violation.reason
+                    );
+                }
+            }
+        }
+
+        let mut with_removal_count = 0;
+        for (line, numbers) in all_numbers.iter().enumerate() {
+            if let Some(removed) = levels_to_remove(numbers, tolerance, &rules) {
+                with_removal_count += 1;
+                if show_removals && !removed.is_empty() {
+                    let levels: Vec<i32> = removed.iter().map(|&i| numbers[i]).collect();
+                    println!("Report {}: remove index {:?} (levels {:?})", line + 1, removed, levels);
+                }
+            }
+        }
         println!("Part1 {}", safe_count);
-        println!("Part2 {}", with_removal_count);
+        println!("Part2 {} (tolerance {})", with_removal_count, tolerance);
     }
 }
 
-pub fn is_safe_sequence(numbers: &[i32]) -> bool {
-    // First check if it's safe without removing any number
-    if is_safe_without_removal(numbers) {
-        return true;
+/// Finds the smallest set of level indices whose removal makes the report safe,
+/// allowing at most `k` removals under `rules`. Returns `None` if more than `k` would be needed.
+///
+/// For each direction, `removals[i]` is the fewest levels dropped before index `i`
+/// such that `numbers[i]` is kept and everything kept so far is safe. Since at most
+/// `k` levels may be skipped, only the previous `k + 1` candidates can precede `i`,
+/// so the whole report is handled in one pass of O(n * k).
+pub fn levels_to_remove(numbers: &[i32], k: usize, rules: &SafetyRules) -> Option<Vec<usize>> {
+    let n = numbers.len();
+    if n <= 1 {
+        return Some(Vec::new());
     }
 
-    // Try removing each number one at a time
-    for skip_idx in 0..numbers.len() {
-        let modified: Vec<i32> = numbers
-            .iter()
-            .enumerate()
-            .filter(|(i, _)| *i != skip_idx)
-            .map(|(_, &x)| x)
-            .collect();
-        
-        if is_safe_without_removal(&modified) {
-            return true;
+    let mut best: Option<Vec<usize>> = None;
+    for &increasing in rules.directions() {
+        let mut removals: Vec<Option<usize>> = vec![None; n];
+        let mut previous: Vec<Option<usize>> = vec![None; n];
+
+        for i in 0..n {
+            // Keep `numbers[i]` as the first level by dropping everything before it.
+            if i <= k {
+                removals[i] = Some(i);
+            }
+            for j in i.saturating_sub(k + 1)..i {
+                let Some(before) = removals[j] else { continue };
+                let cost = before + (i - j - 1);
+                if cost <= k
+                    && rules.is_safe_step(numbers[j], numbers[i], increasing)
+                    && removals[i].is_none_or(|current| cost < current)
+                {
+                    removals[i] = Some(cost);
+                    previous[i] = Some(j);
+                }
+            }
+        }
+
+        // Keep `numbers[last]` as the final level by dropping everything after it.
+        let end = (n.saturating_sub(k + 1)..n)
+            .filter_map(|last| removals[last].map(|cost| (cost + (n - 1 - last), last)))
+            .filter(|&(cost, _)| cost <= k)
+            .min();
+
+        if let Some((cost, last)) = end {
+            if best.as_ref().is_none_or(|removed| cost < removed.len()) {
+                let mut kept = vec![false; n];
+                let mut current = Some(last);
+                while let Some(i) = current {
+                    kept[i] = true;
+                    current = previous[i];
+                }
+                best = Some((0..n).filter(|&i| !kept[i]).collect());
+            }
         }
     }
-    false
+    best
 }
 
-fn is_safe_without_removal(numbers: &[i32]) -> bool {
+/// Which way a report is allowed to move.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AllowedDirection {
+    Increasing,
+    Decreasing,
+    Either,
+}
 
-    let first_diff = numbers[1] - numbers[0];
-    if first_diff == 0 {
-        return false;
+/// What counts as a safe report.
+#[derive(Debug, Clone, Copy)]
+pub struct SafetyRules {
+    /// Smallest allowed difference between adjacent levels.
+    pub min_step: i32,
+    /// Largest allowed difference between adjacent levels.
+    pub max_step: i32,
+    /// Strict monotonicity rejects equal adjacent levels; non-strict lets them through.
+    pub strict: bool,
+    pub direction: AllowedDirection,
+}
+
+impl Default for SafetyRules {
+    fn default() -> Self {
+        SafetyRules { min_step: 1, max_step: 3, strict: true, direction: AllowedDirection::Either }
     }
+}
 
-    let is_increasing = first_diff > 0;
-    
-    for window in numbers.windows(2) {
-        let diff = window[1] - window[0];
-        if diff == 0 || diff.abs() > 3 || 
-           (is_increasing && diff < 0) || 
-           (!is_increasing && diff > 0) {
-            return false;
+impl SafetyRules {
+    /// Builds the rules from `--min-step`, `--max-step`, `--non-strict` and `--direction`.
+    fn from_args() -> Result<Self, String> {
+        let mut rules = SafetyRules::default();
+        if let Some(value) = utils::flag_value("--min-step") {
+            rules.min_step = value.parse().map_err(|_| format!("invalid --min-step '{}'", value))?;
+        }
+        if let Some(value) = utils::flag_value("--max-step") {
+            rules.max_step = value.parse().map_err(|_| format!("invalid --max-step '{}'", value))?;
+        }
+        rules.strict = !utils::has_flag("--non-strict");
+        if let Some(value) = utils::flag_value("--direction") {
+            rules.direction = match value.as_str() {
+                "inc" | "increasing" => AllowedDirection::Increasing,
+                "dec" | "decreasing" => AllowedDirection::Decreasing,
+                "any" | "either" => AllowedDirection::Either,
+                _ => return Err(format!("invalid --direction '{}' (inc, dec or any)", value)),
+            };
+        }
+        if rules.min_step < 0 || rules.min_step > rules.max_step {
+            return Err(format!("invalid step range {}..={}", rules.min_step, rules.max_step));
         }
+        Ok(rules)
     }
-    true
+
+    fn directions(&self) -> &'static [bool] {
+        match self.direction {
+            AllowedDirection::Increasing => &[true],
+            AllowedDirection::Decreasing => &[false],
+            AllowedDirection::Either => &[true, false],
+        }
+    }
+
+    /// Checks a single step assuming the report moves in the given direction.
+    fn check_step(&self, from: i32, to: i32, increasing: bool) -> Result<(), Reason> {
+        let diff = to - from;
+        if diff == 0 {
+            return if self.strict { Err(Reason::NoChange) } else { Ok(()) };
+        }
+        if (diff > 0) != increasing {
+            return Err(Reason::WrongDirection { increasing: diff > 0 });
+        }
+        let step = diff.abs();
+        if step < self.min_step {
+            return Err(Reason::StepTooSmall { step, min: self.min_step });
+        }
+        if step > self.max_step {
+            return Err(Reason::StepTooLarge { step, max: self.max_step });
+        }
+        Ok(())
+    }
+
+    fn is_safe_step(&self, from: i32, to: i32, increasing: bool) -> bool {
+        self.check_step(from, to, increasing).is_ok()
+    }
+}
+
+/// Why a window of two adjacent levels is unsafe.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Reason {
+    NoChange,
+    StepTooSmall { step: i32, min: i32 },
+    StepTooLarge { step: i32, max: i32 },
+    /// The step goes the other way from what the report (or the rules) require.
+    WrongDirection { increasing: bool },
 }
 
+impl fmt::Display for Reason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Reason::NoChange => write!(f, "levels are equal"),
+            Reason::StepTooSmall { step, min } => write!(f, "step of {} is below the minimum {}", step, min),
+            Reason::StepTooLarge { step, max } => write!(f, "step of {} exceeds the maximum {}", step, max),
+            Reason::WrongDirection { increasing: true } => write!(f, "increases in a decreasing report"),
+            Reason::WrongDirection { increasing: false } => write!(f, "decreases in an increasing report"),
+        }
+    }
+}
+
+/// The first unsafe window of a report: `numbers[index]` to `numbers[index + 1]`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Violation {
+    pub index: usize,
+    pub window: (i32, i32),
+    pub reason: Reason,
+}
+
+/// Returns the first offending window, or `None` if the report is safe.
+/// Reports with fewer than two levels have no windows and are always safe.
+pub fn diagnose(numbers: &[i32], rules: &SafetyRules) -> Option<Violation> {
+    // The first non-zero step fixes the direction unless the rules already do.
+    let mut increasing = match rules.direction {
+        AllowedDirection::Increasing => Some(true),
+        AllowedDirection::Decreasing => Some(false),
+        AllowedDirection::Either => None,
+    };
+
+    for (index, window) in numbers.windows(2).enumerate() {
+        let (from, to) = (window[0], window[1]);
+        // Equal levels are judged before direction, so a flat step needs no direction yet.
+        let direction = match increasing {
+            Some(direction) => direction,
+            None if to != from => *increasing.insert(to > from),
+            None => true,
+        };
+        if let Err(reason) = rules.check_step(from, to, direction) {
+            return Some(Violation { index, window: (from, to), reason });
+        }
+    }
+    None
+}
+
+fn is_safe_without_removal(numbers: &[i32], rules: &SafetyRules) -> bool {
+    diagnose(numbers, rules).is_none()
+}
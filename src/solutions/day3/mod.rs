@@ -1,59 +1,505 @@
-use regex::Regex;
 use crate::utils;
 
-fn part1(input: &str) -> i32 {
-    let re = Regex::new(r"mul\((\d+),(\d+)\)").unwrap();
-    
-    // Find all matches and sum their products
-    re.captures_iter(input)
-        .map(|cap| {
-            // Parse the two numbers and multiply them
-            let x: i32 = cap[1].parse().unwrap();
-            let y: i32 = cap[2].parse().unwrap();
-            x * y
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+use std::ops::Range;
+
+/// Longest argument accepted by the puzzle, e.g. `mul(123,4)`.
+const MAX_DIGITS: usize = 3;
+
+/// State shared by all instructions while interpreting the memory.
+#[derive(Debug, Clone, Copy)]
+pub struct Machine {
+    pub enabled: bool,
+    pub total: i128,
+}
+
+impl Default for Machine {
+    fn default() -> Self {
+        Machine { enabled: true, total: 0 }
+    }
+}
+
+/// One instruction of the corrupted-memory language, written as `name(arg,...)`.
+/// Adding an instruction means implementing this trait and registering it;
+/// the lexer picks it up by name and arity.
+pub trait Handler {
+    /// Name as it appears in memory, e.g. `mul` or `don't`.
+    fn name(&self) -> &'static str;
+    /// Number of comma-separated numeric arguments between the parentheses.
+    fn arity(&self) -> usize;
+    fn execute(&self, args: &[i64], machine: &mut Machine) -> Result<(), Overflow>;
+}
+
+/// Returned by a handler whose result no longer fits into the total.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Overflow;
+
+#[derive(Debug)]
+pub enum EvalError {
+    Io(io::Error),
+    /// The instruction starting at this byte offset overflowed the total.
+    Overflow { offset: usize },
+}
+
+impl fmt::Display for EvalError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EvalError::Io(e) => write!(f, "failed to read memory: {}", e),
+            EvalError::Overflow { offset } => write!(f, "total overflowed at byte {}", offset),
+        }
+    }
+}
+
+impl std::error::Error for EvalError {}
+
+impl From<io::Error> for EvalError {
+    fn from(e: io::Error) -> Self {
+        EvalError::Io(e)
+    }
+}
+
+/// `mul(a,b)`: adds `a * b` to the total while enabled.
+pub struct Mul;
+/// `do()`: enables subsequent `mul` instructions.
+pub struct Do;
+/// `don't()`: disables subsequent `mul` instructions.
+pub struct Dont;
+
+impl Handler for Mul {
+    fn name(&self) -> &'static str { "mul" }
+    fn arity(&self) -> usize { 2 }
+    fn execute(&self, args: &[i64], machine: &mut Machine) -> Result<(), Overflow> {
+        if machine.enabled {
+            let product = (args[0] as i128).checked_mul(args[1] as i128).ok_or(Overflow)?;
+            machine.total = machine.total.checked_add(product).ok_or(Overflow)?;
+        }
+        Ok(())
+    }
+}
+
+impl Handler for Do {
+    fn name(&self) -> &'static str { "do" }
+    fn arity(&self) -> usize { 0 }
+    fn execute(&self, _args: &[i64], machine: &mut Machine) -> Result<(), Overflow> {
+        machine.enabled = true;
+        Ok(())
+    }
+}
+
+impl Handler for Dont {
+    fn name(&self) -> &'static str { "don't" }
+    fn arity(&self) -> usize { 0 }
+    fn execute(&self, _args: &[i64], machine: &mut Machine) -> Result<(), Overflow> {
+        machine.enabled = false;
+        Ok(())
+    }
+}
+
+/// A recognised instruction and the bytes it was read from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Token {
+    /// Index of the handler in the instruction set the lexer was built with.
+    pub handler: usize,
+    pub args: Vec<i64>,
+    pub span: Range<usize>,
+}
+
+/// A near-miss: text that starts like an instruction but is not a valid one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rejection {
+    pub handler: usize,
+    /// From the start of the name up to and including the offending byte.
+    pub span: Range<usize>,
+    pub reason: String,
+}
+
+/// Everything the lexer reports, in order of appearance.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Lexeme {
+    Token(Token),
+    Rejected(Rejection),
+}
+
+/// Result of trying to read an instruction at one position.
+enum Scan {
+    /// A complete instruction of this many bytes.
+    Match { handler: usize, args: Vec<i64>, len: usize },
+    /// The name matched but the rest did not; `len` covers the offending byte.
+    Rejected { handler: usize, len: usize, reason: String },
+    /// Nothing valid starts here.
+    NoMatch,
+    /// The input ends in the middle of what could still become an instruction.
+    Incomplete,
+}
+
+/// Why `scan_instruction` stopped.
+enum ScanError {
+    /// The input ran out first.
+    Incomplete,
+    /// Not even the name matched.
+    NotInstruction,
+    /// The name matched but the bytes up to `at` break the syntax.
+    Rejected { at: usize, reason: String },
+}
+
+/// Single-pass lexer over the memory dump, yielding tokens in order.
+pub struct Lexer<'a> {
+    input: &'a [u8],
+    handlers: &'a [Box<dyn Handler>],
+    pos: usize,
+    /// False while more input may follow, as with one chunk of a stream.
+    complete: bool,
+    /// Length of the longest instruction any handler can match.
+    longest: usize,
+}
+
+impl<'a> Lexer<'a> {
+    pub fn new(input: &'a str, handlers: &'a [Box<dyn Handler>]) -> Self {
+        Lexer::from_bytes(input.as_bytes(), handlers)
+    }
+
+    pub fn from_bytes(input: &'a [u8], handlers: &'a [Box<dyn Handler>]) -> Self {
+        Lexer { input, handlers, pos: 0, complete: true, longest: longest_instruction(handlers) }
+    }
+
+    /// Lexes one chunk of a larger input. Lexing stops in front of an instruction
+    /// cut off by the end of the chunk; `consumed` tells where to resume. At most
+    /// the longest instruction's length is left unconsumed, so near-misses that
+    /// would need more lookahead than that are skipped rather than reported.
+    pub fn partial(input: &'a [u8], handlers: &'a [Box<dyn Handler>]) -> Self {
+        Lexer { input, handlers, pos: 0, complete: false, longest: longest_instruction(handlers) }
+    }
+
+    /// Number of bytes fully processed so far.
+    pub fn consumed(&self) -> usize {
+        self.pos
+    }
+
+    /// Like `next`, but also reports the near-misses that were skipped.
+    pub fn next_lexeme(&mut self) -> Option<Lexeme> {
+        while self.pos < self.input.len() {
+            let start = self.pos;
+            match scan_at(self.input, start, self.handlers) {
+                Scan::Match { handler, args, len } => {
+                    self.pos += len;
+                    return Some(Lexeme::Token(Token { handler, args, span: start..start + len }));
+                }
+                Scan::Rejected { handler, len, reason } => {
+                    // The offending byte may itself start an instruction, e.g. `mul(mul(2,3)`.
+                    self.pos += 1;
+                    return Some(Lexeme::Rejected(Rejection { handler, span: start..start + len, reason }));
+                }
+                // With `longest` bytes in view a token would already have matched.
+                Scan::Incomplete if !self.complete && self.input.len() - start < self.longest => return None,
+                Scan::NoMatch | Scan::Incomplete => self.pos += 1,
+            }
+        }
+        None
+    }
+}
+
+impl Iterator for Lexer<'_> {
+    type Item = Token;
+
+    fn next(&mut self) -> Option<Token> {
+        loop {
+            match self.next_lexeme()? {
+                Lexeme::Token(token) => return Some(token),
+                Lexeme::Rejected(_) => continue,
+            }
+        }
+    }
+}
+
+/// Byte length of the longest instruction the handlers accept, e.g. 12 for `mul(123,456)`.
+fn longest_instruction(handlers: &[Box<dyn Handler>]) -> usize {
+    handlers
+        .iter()
+        .map(|handler| {
+            let arity = handler.arity();
+            handler.name().len() + "()".len() + arity * MAX_DIGITS + arity.saturating_sub(1)
         })
-        .sum()
+        .max()
+        .unwrap_or(0)
 }
 
-fn part2(input: &str) -> i32 {
-    let mul_re = Regex::new(r"mul\((\d+),(\d+)\)").unwrap();
-    let mut sum = 0;
-    let mut enabled = true; 
-    let mut current_pos = 0;
+/// Tries every registered instruction at `input[pos..]`.
+fn scan_at(input: &[u8], pos: usize, handlers: &[Box<dyn Handler>]) -> Scan {
+    let mut incomplete = false;
+    let mut rejected = None;
+    for (index, handler) in handlers.iter().enumerate() {
+        match scan_instruction(&input[pos..], handler.as_ref()) {
+            Ok((args, len)) => return Scan::Match { handler: index, args, len },
+            Err(ScanError::Incomplete) => incomplete = true,
+            Err(ScanError::Rejected { at, reason }) => {
+                rejected.get_or_insert(Scan::Rejected { handler: index, len: at + 1, reason });
+            }
+            Err(ScanError::NotInstruction) => {}
+        }
+    }
+    if incomplete {
+        Scan::Incomplete
+    } else {
+        rejected.unwrap_or(Scan::NoMatch)
+    }
+}
+
+/// Reads `name(arg,...)` from the start of `rest`.
+fn scan_instruction(rest: &[u8], handler: &dyn Handler) -> Result<(Vec<i64>, usize), ScanError> {
+    let mut cursor = Cursor { rest, pos: 0 };
+    if !cursor.expect(handler.name().as_bytes())? {
+        return Err(ScanError::NotInstruction);
+    }
+    if !cursor.expect(b"(")? {
+        // Only `name (` is close enough to report; `name` followed by anything else is just text.
+        let gap = rest[cursor.pos..].iter().take_while(|b| b.is_ascii_whitespace()).count();
+        return match rest.get(cursor.pos + gap) {
+            Some(b'(') if gap > 0 => {
+                cursor.pos += gap;
+                Err(cursor.reject("whitespace before '('"))
+            }
+            None if gap > 0 => Err(ScanError::Incomplete),
+            _ => Err(ScanError::NotInstruction),
+        };
+    }
+    let mut args = Vec::with_capacity(handler.arity());
+    for i in 0..handler.arity() {
+        if i > 0 && !cursor.expect(b",")? {
+            return Err(cursor.unexpected("','"));
+        }
+        args.push(cursor.number()?);
+    }
+    if !cursor.expect(b")")? {
+        return Err(cursor.unexpected("')'"));
+    }
+    Ok((args, cursor.pos))
+}
+
+struct Cursor<'a> {
+    rest: &'a [u8],
+    pos: usize,
+}
+
+impl Cursor<'_> {
+    /// Consumes `literal` if it comes next; `Ok(false)` leaves the cursor on the first mismatch.
+    fn expect(&mut self, literal: &[u8]) -> Result<bool, ScanError> {
+        for &byte in literal {
+            match self.rest.get(self.pos) {
+                Some(&b) if b == byte => self.pos += 1,
+                Some(_) => return Ok(false),
+                None => return Err(ScanError::Incomplete),
+            }
+        }
+        Ok(true)
+    }
+
+    fn reject(&self, reason: &str) -> ScanError {
+        ScanError::Rejected { at: self.pos, reason: reason.to_string() }
+    }
 
-    for cap in mul_re.find_iter(input) {
-        let mul_start = cap.start();
-        
-        // mul命令の前にdon't()があるか確認
-        let preceding_text = &input[current_pos..mul_start];
-        if let Some(dont_pos) = preceding_text.rfind("don't()") {
-            let do_pos = preceding_text[dont_pos..].rfind("do()");
-            // don't()の後にdo()がない場合は無効
-            enabled = do_pos.is_some();
-        } else if let Some(_) = preceding_text.rfind("do()") {
-            enabled = true;
+    fn unexpected(&self, expected: &str) -> ScanError {
+        let found = self.rest[self.pos];
+        if found.is_ascii_whitespace() {
+            self.reject("whitespace inside the argument list")
+        } else {
+            self.reject(&format!("expected {}, found '{}'", expected, char::from(found).escape_default()))
         }
+    }
 
-        if enabled {
-            // 有効な場合のみ計算して加算
-            let cap = mul_re.captures(cap.as_str()).unwrap();
-            let x: i32 = cap[1].parse().unwrap();
-            let y: i32 = cap[2].parse().unwrap();
-            sum += x * y;
+    fn number(&mut self) -> Result<i64, ScanError> {
+        let start = self.pos;
+        while self.rest.get(self.pos).is_some_and(u8::is_ascii_digit) {
+            if self.pos - start == MAX_DIGITS {
+                // Stop at the first extra digit instead of scanning the whole run.
+                return Err(self.reject(&format!("argument has more than {} digits", MAX_DIGITS)));
+            }
+            self.pos += 1;
         }
+        if self.pos == self.rest.len() {
+            // More digits or the separator may still follow.
+            return Err(ScanError::Incomplete);
+        }
+        if self.pos == start {
+            return Err(self.unexpected("a number"));
+        }
+        Ok(self.rest[start..self.pos]
+            .iter()
+            .fold(0, |value, &digit| value * 10 + (digit - b'0') as i64))
+    }
+}
 
-        current_pos = mul_start + 1;
+/// Size of the read buffer used by `interpret_stream`.
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// Runs every recognised instruction in order on a fresh machine, reading the
+/// memory dump chunk by chunk so its size does not matter. Bytes after the last
+/// complete instruction of a chunk are carried over, so instructions split
+/// across chunk boundaries are still recognised; the carry never exceeds one
+/// instruction, so each refill costs time proportional to the chunk.
+pub fn interpret_stream<R: BufRead>(mut reader: R, handlers: &[Box<dyn Handler>]) -> Result<Machine, EvalError> {
+    let mut machine = Machine::default();
+    let mut pending: Vec<u8> = Vec::new();
+    // Byte offset of `pending[0]` in the whole stream.
+    let mut offset = 0;
+
+    loop {
+        let chunk = reader.fill_buf()?;
+        let at_end = chunk.is_empty();
+        pending.extend_from_slice(chunk);
+        let read = chunk.len();
+        reader.consume(read);
+
+        let mut lexer = if at_end {
+            Lexer::from_bytes(&pending, handlers)
+        } else {
+            Lexer::partial(&pending, handlers)
+        };
+        for token in lexer.by_ref() {
+            handlers[token.handler]
+                .execute(&token.args, &mut machine)
+                .map_err(|Overflow| EvalError::Overflow { offset: offset + token.span.start })?;
+        }
+        if at_end {
+            return Ok(machine);
+        }
+        let consumed = lexer.consumed();
+        pending.drain(..consumed);
+        offset += consumed;
     }
+}
 
-    sum
+/// One line of the `--trace` report.
+#[derive(Debug, Clone)]
+pub enum TraceEntry {
+    Executed { token: Token, enabled: bool, contribution: i128 },
+    Rejected(Rejection),
 }
+
+/// Interprets `input` like `interpret_stream`, recording every instruction and near-miss.
+pub fn trace(input: &str, handlers: &[Box<dyn Handler>]) -> Result<(Machine, Vec<TraceEntry>), EvalError> {
+    let mut machine = Machine::default();
+    let mut entries = Vec::new();
+    let mut lexer = Lexer::new(input, handlers);
+    while let Some(lexeme) = lexer.next_lexeme() {
+        match lexeme {
+            Lexeme::Token(token) => {
+                let (enabled, before) = (machine.enabled, machine.total);
+                handlers[token.handler]
+                    .execute(&token.args, &mut machine)
+                    .map_err(|Overflow| EvalError::Overflow { offset: token.span.start })?;
+                entries.push(TraceEntry::Executed { token, enabled, contribution: machine.total - before });
+            }
+            Lexeme::Rejected(rejection) => entries.push(TraceEntry::Rejected(rejection)),
+        }
+    }
+    Ok((machine, entries))
+}
+
+/// 1-based line and column of a byte offset.
+struct LineIndex {
+    starts: Vec<usize>,
+}
+
+impl LineIndex {
+    fn new(input: &str) -> Self {
+        let starts = std::iter::once(0)
+            .chain(input.match_indices('\n').map(|(i, _)| i + 1))
+            .collect();
+        LineIndex { starts }
+    }
+
+    fn locate(&self, input: &str, offset: usize) -> (usize, usize) {
+        let line = self.starts.partition_point(|&start| start <= offset) - 1;
+        let column = String::from_utf8_lossy(&input.as_bytes()[self.starts[line]..offset]).chars().count();
+        (line + 1, column + 1)
+    }
+}
+
+fn print_trace(input: &str, handlers: &[Box<dyn Handler>]) {
+    let (machine, entries) = match trace(input, handlers) {
+        Ok(result) => result,
+        Err(e) => {
+            println!("Trace: {}", e);
+            return;
+        }
+    };
+    let lines = LineIndex::new(input);
+    // Spans can cover newlines or stray control bytes; keep each entry on one line.
+    let text = |span: &Range<usize>| -> String {
+        String::from_utf8_lossy(&input.as_bytes()[span.clone()])
+            .chars()
+            .map(|c| if c.is_control() { c.escape_default().to_string() } else { c.to_string() })
+            .collect()
+    };
+
+    println!("{:>8} {:>9}  {:<16} result", "offset", "line:col", "instruction");
+    let (mut counted, mut skipped, mut rejected) = (0, 0, 0);
+    for entry in &entries {
+        match entry {
+            TraceEntry::Executed { token, enabled, contribution } => {
+                let (line, column) = lines.locate(input, token.span.start);
+                let status = match (handlers[token.handler].arity(), enabled) {
+                    (0, _) => "control".to_string(),
+                    (_, true) => format!("enabled  {:+}", contribution),
+                    (_, false) => "disabled".to_string(),
+                };
+                if handlers[token.handler].arity() > 0 {
+                    if *enabled { counted += 1 } else { skipped += 1 }
+                }
+                println!("{:>8} {:>9}  {:<16} {}", token.span.start, format!("{}:{}", line, column), text(&token.span), status);
+            }
+            TraceEntry::Rejected(rejection) => {
+                rejected += 1;
+                let (line, column) = lines.locate(input, rejection.span.start);
+                println!(
+                    "{:>8} {:>9}  {:<16} rejected: {}",
+                    rejection.span.start, format!("{}:{}", line, column), text(&rejection.span), rejection.reason
+                );
+            }
+        }
+    }
+    println!(
+        "Trace: {} counted, {} disabled, {} rejected, total {}",
+        counted, skipped, rejected, machine.total
+    );
+}
+
+fn evaluate(path: &str, handlers: &[Box<dyn Handler>]) -> Result<i128, EvalError> {
+    let reader = BufReader::with_capacity(CHUNK_SIZE, File::open(path)?);
+    Ok(interpret_stream(reader, handlers)?.total)
+}
+
+fn part1(path: &str) -> Result<i128, EvalError> {
+    let handlers: Vec<Box<dyn Handler>> = vec![Box::new(Mul)];
+    evaluate(path, &handlers)
+}
+
+fn part2_handlers() -> Vec<Box<dyn Handler>> {
+    vec![Box::new(Mul), Box::new(Do), Box::new(Dont)]
+}
+
+fn part2(path: &str) -> Result<i128, EvalError> {
+    evaluate(path, &part2_handlers())
+}
+
 pub fn solve(day: u32) {
     println!("Started Day{}!",day );
 
-    if let Ok(contents) = utils::read_file(&format!("src/solutions/day{}/input.txt", day)) {
-        let result = part1(&contents); 
-        println!("Part1: Sum of multiplications: {}", result);
-        let result2 = part2(&contents); 
-        println!("Part2: Sum of multiplications: {}", result2);
+    // Both parts stream the file, so memory dumps of any size can be evaluated.
+    let path = format!("src/solutions/day{}/input.txt", day);
+    match part1(&path) {
+        Ok(result) => println!("Part1: Sum of multiplications: {}", result),
+        Err(e) => println!("Part1: {}", e),
+    }
+    match part2(&path) {
+        Ok(result) => println!("Part2: Sum of multiplications: {}", result),
+        Err(e) => println!("Part2: {}", e),
     }
-}
\ No newline at end of file
+    if utils::has_flag("--trace") {
+        if let Ok(contents) = utils::read_file(&path) {
+            print_trace(&contents, &part2_handlers());
+        }
+    }
+}
@@ -1,79 +1,265 @@
 use crate::utils;
 
-fn part1(grid: &[Vec<char>]) -> usize {
-    let directions = [
-        (0, 1),  // Horizontal: Left to Right
-        (0, -1), // Horizontal: Right to Left
-        (1, 0),  // Vertical: Top to Bottom
-        (-1, 0), // Vertical: Bottom to Top
-        (1, 1),  // Diagonal: Top-Left to Bottom-Right
-        (-1, -1),// Diagonal: Bottom-Right to Top-Left
-        (1, -1), // Diagonal: Top-Right to Bottom-Left
-        (-1, 1), // Diagonal: Bottom-Left to Top-Right
+use std::collections::HashMap;
+
+/// The eight straight lines a word can follow through the grid.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Direction {
+    East,
+    West,
+    South,
+    North,
+    SouthEast,
+    NorthWest,
+    SouthWest,
+    NorthEast,
+}
+
+impl Direction {
+    pub const ALL: [Direction; 8] = [
+        Direction::East,
+        Direction::West,
+        Direction::South,
+        Direction::North,
+        Direction::SouthEast,
+        Direction::NorthWest,
+        Direction::SouthWest,
+        Direction::NorthEast,
     ];
 
-    let word = vec!['X', 'M', 'A', 'S'];
+    /// (row, column) step.
+    fn delta(self) -> (isize, isize) {
+        match self {
+            Direction::East      => (0, 1),  // Horizontal: Left to Right
+            Direction::West      => (0, -1), // Horizontal: Right to Left
+            Direction::South     => (1, 0),  // Vertical: Top to Bottom
+            Direction::North     => (-1, 0), // Vertical: Bottom to Top
+            Direction::SouthEast => (1, 1),  // Diagonal: Top-Left to Bottom-Right
+            Direction::NorthWest => (-1, -1),// Diagonal: Bottom-Right to Top-Left
+            Direction::SouthWest => (1, -1), // Diagonal: Top-Right to Bottom-Left
+            Direction::NorthEast => (-1, 1), // Diagonal: Bottom-Left to Top-Right
+        }
+    }
+
+    fn parse(name: &str) -> Option<Direction> {
+        match name.to_ascii_uppercase().as_str() {
+            "E" => Some(Direction::East),
+            "W" => Some(Direction::West),
+            "S" => Some(Direction::South),
+            "N" => Some(Direction::North),
+            "SE" => Some(Direction::SouthEast),
+            "NW" => Some(Direction::NorthWest),
+            "SW" => Some(Direction::SouthWest),
+            "NE" => Some(Direction::NorthEast),
+            _ => None,
+        }
+    }
+}
+
+/// Which lines to follow and whether they continue past the grid edges.
+#[derive(Debug, Clone)]
+pub struct SearchOptions {
+    pub directions: Vec<Direction>,
+    /// Lines leaving one edge re-enter on the opposite edge.
+    pub wrap: bool,
+}
+
+impl Default for SearchOptions {
+    fn default() -> Self {
+        SearchOptions { directions: Direction::ALL.to_vec(), wrap: false }
+    }
+}
+
+/// One occurrence of a dictionary word.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WordMatch {
+    pub word: String,
+    /// (row, column) of the first letter.
+    pub start: (usize, usize),
+    pub direction: Direction,
+}
+
+/// Prefix tree over the dictionary, so every word is matched in one walk per line.
+struct Trie {
+    nodes: Vec<TrieNode>,
+    depth: usize,
+}
+
+#[derive(Default)]
+struct TrieNode {
+    children: HashMap<char, usize>,
+    /// Index into the dictionary of the word ending here.
+    word: Option<usize>,
+}
+
+impl Trie {
+    fn new(words: &[&str]) -> Self {
+        let mut trie = Trie { nodes: vec![TrieNode::default()], depth: 0 };
+        for (index, word) in words.iter().enumerate() {
+            let mut node = 0;
+            for ch in word.chars() {
+                node = match trie.nodes[node].children.get(&ch) {
+                    Some(&next) => next,
+                    None => {
+                        trie.nodes.push(TrieNode::default());
+                        let next = trie.nodes.len() - 1;
+                        trie.nodes[node].children.insert(ch, next);
+                        next
+                    }
+                };
+            }
+            trie.nodes[node].word.get_or_insert(index);
+            trie.depth = trie.depth.max(word.chars().count());
+        }
+        trie
+    }
+}
+
+/// Finds every occurrence of every word along the selected directions.
+pub fn search(grid: &[Vec<char>], words: &[&str], options: &SearchOptions) -> Vec<WordMatch> {
+    let trie = Trie::new(words);
     let rows = grid.len() as isize;
-    let cols = grid[0].len() as isize;
-    let mut count = 0;
+    let cols = grid.first().map_or(0, |row| row.len()) as isize;
+    let mut matches = Vec::new();
 
     for r in 0..rows {
         for c in 0..cols {
-            for (dr, dc) in &directions {
-                let mut found = true;
-                for (i, &ch) in word.iter().enumerate() {
-                    let nr = r + dr * i as isize;
-                    let nc = c + dc * i as isize;
-                    if nr < 0 || nr >= rows || nc < 0 || nc >= cols || grid[nr as usize][nc as usize] != ch {
-                        found = false;
+            for &direction in &options.directions {
+                let (dr, dc) = direction.delta();
+                let mut node = 0;
+                for i in 0..trie.depth as isize {
+                    let (mut nr, mut nc) = (r + dr * i, c + dc * i);
+                    if options.wrap {
+                        nr = nr.rem_euclid(rows);
+                        nc = nc.rem_euclid(cols);
+                    } else if nr < 0 || nr >= rows || nc < 0 || nc >= cols {
                         break;
                     }
-                }
-                if found {
-                    count += 1;
+                    let Some(&next) = grid[nr as usize].get(nc as usize).and_then(|ch| trie.nodes[node].children.get(ch)) else {
+                        break;
+                    };
+                    node = next;
+                    if let Some(word) = trie.nodes[node].word {
+                        matches.push(WordMatch {
+                            word: words[word].to_string(),
+                            start: (r as usize, c as usize),
+                            direction,
+                        });
+                    }
                 }
             }
         }
     }
 
-    count
+    matches
 }
 
-fn part2(grid: &[Vec<char>]) -> usize {
-    let rows = grid.len();
-    let cols = grid[0].len();
-    let mut count = 0;
-
-
-
-    // Iterate through each cell in the grid, excluding boundary cells
-    for i in 1..rows - 1 {
-        for j in 1..cols - 1 {
-            let mut left_down = false;
-            let mut right_up = false;
-            // Check if the center is 'A'
-            if grid[i][j] == 'A' {
-                // Check for Forward X-MAS (MAS diagonal top-left to bottom-right)
-                if grid[i - 1][j - 1] == 'M' && grid[i + 1][j + 1] == 'S' || 
-                   grid[i - 1][j - 1] == 'S' && grid[i + 1][j + 1] == 'M' {
-                    // println!("Started left T to BL X-{}, Y={}!",i, j );
-                    left_down = true;
-                }
-                
-                // Check for Backward X-MAS (MAS diagonal bottom-left to top-right)
-                if grid[i - 1][j + 1] == 'S' && grid[i + 1][j - 1] == 'M'  ||
-                   grid[i - 1][j + 1] == 'M' && grid[i + 1][j - 1] == 'S' {
-                    // println!("Started  X-{}, Y={}!",i, j );
-                    right_up = true;
+/// Copy of the grid showing only the letters that belong to a match.
+pub fn highlight(grid: &[Vec<char>], matches: &[WordMatch], wrap: bool) -> Vec<String> {
+    let rows = grid.len() as isize;
+    let cols = grid.first().map_or(0, |row| row.len()) as isize;
+    let mut keep = vec![vec![false; cols as usize]; rows as usize];
+    for m in matches {
+        let (dr, dc) = m.direction.delta();
+        for i in 0..m.word.chars().count() as isize {
+            let (mut r, mut c) = (m.start.0 as isize + dr * i, m.start.1 as isize + dc * i);
+            if wrap {
+                r = r.rem_euclid(rows);
+                c = c.rem_euclid(cols);
+            }
+            keep[r as usize][c as usize] = true;
+        }
+    }
+    grid.iter()
+        .zip(&keep)
+        .map(|(row, keep)| row.iter().zip(keep).map(|(&ch, &k)| if k { ch } else { '.' }).collect())
+        .collect()
+}
+
+fn part1(grid: &[Vec<char>]) -> usize {
+    search(grid, &["XMAS"], &SearchOptions::default()).len()
+}
+
+/// A small 2D shape such as `M.S/.A./M.S`: rows separated by `/`, `.` matches any letter.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Template {
+    cells: Vec<Vec<Option<char>>>,
+}
+
+impl Template {
+    pub fn parse(pattern: &str) -> Result<Template, String> {
+        let cells: Vec<Vec<Option<char>>> = pattern
+            .split('/')
+            .map(|row| row.chars().map(|ch| if ch == '.' { None } else { Some(ch) }).collect())
+            .collect();
+        let width = cells[0].len();
+        if width == 0 || cells.iter().any(|row| row.len() != width) {
+            return Err(format!("template '{}' must be a non-empty rectangle", pattern));
+        }
+        Ok(Template { cells })
+    }
+
+    fn rotate(&self) -> Template {
+        // Clockwise: the first column, read bottom-up, becomes the first row.
+        let (rows, cols) = (self.cells.len(), self.cells[0].len());
+        let cells = (0..cols).map(|c| (0..rows).rev().map(|r| self.cells[r][c]).collect()).collect();
+        Template { cells }
+    }
+
+    fn reflect(&self) -> Template {
+        let cells = self.cells.iter().map(|row| row.iter().rev().copied().collect()).collect();
+        Template { cells }
+    }
+
+    /// All distinct rotations and reflections, the template itself first.
+    pub fn variants(&self) -> Vec<Template> {
+        let mut variants: Vec<Template> = Vec::new();
+        for start in [self.clone(), self.reflect()] {
+            let mut current = start;
+            for _ in 0..4 {
+                let next = current.rotate();
+                if !variants.contains(&current) {
+                    variants.push(current);
                 }
-                if left_down && right_up{
-                    count += 1;
+                current = next;
+            }
+        }
+        variants
+    }
+
+    fn matches_at(&self, grid: &[Vec<char>], top: usize, left: usize) -> bool {
+        self.cells.iter().enumerate().all(|(r, row)| {
+            row.iter().enumerate().all(|(c, cell)| match cell {
+                None => grid.get(top + r).and_then(|line| line.get(left + c)).is_some(),
+                Some(ch) => grid.get(top + r).and_then(|line| line.get(left + c)) == Some(ch),
+            })
+        })
+    }
+}
+
+/// Top-left corner of every placement of any variant of `template`, with the
+/// variant that matched (an index into `template.variants()`).
+pub fn find_template(grid: &[Vec<char>], template: &Template) -> Vec<((usize, usize), usize)> {
+    let mut found = Vec::new();
+    for (index, variant) in template.variants().iter().enumerate() {
+        for top in 0..grid.len() {
+            for left in 0..grid[top].len() {
+                if variant.matches_at(grid, top, left) {
+                    found.push(((top, left), index));
                 }
             }
         }
     }
+    found.sort();
+    found
+}
+
+/// Two diagonal "MAS" crossing on their shared 'A'.
+const X_MAS: &str = "M.S/.A./M.S";
 
-    count
+fn part2(grid: &[Vec<char>]) -> usize {
+    let template = Template::parse(X_MAS).expect("built-in template is valid");
+    find_template(grid, &template).len()
 }
 
 
@@ -89,5 +275,49 @@ pub fn solve(day: u32) {
         let part2 = part2(&grid);
         println!("Part1:  {}", part1);
         println!("Part2:  {}", part2);
+
+        if let Some(pattern) = utils::flag_value("--template") {
+            match Template::parse(&pattern) {
+                Ok(template) => println!("Template {}: {}", pattern, find_template(&grid, &template).len()),
+                Err(e) => println!("{}", e),
+            }
+        }
+
+        if let Some(words) = utils::flag_value("--words") {
+            match search_options_from_args() {
+                Ok(options) => report_search(&grid, &words, &options),
+                Err(e) => println!("{}", e),
+            }
+        }
+    }
+}
+
+/// Reads `--directions E,SE,...` and `--wrap`.
+fn search_options_from_args() -> Result<SearchOptions, String> {
+    let mut options = SearchOptions { wrap: utils::has_flag("--wrap"), ..SearchOptions::default() };
+    if let Some(list) = utils::flag_value("--directions") {
+        options.directions = list
+            .split(',')
+            .map(|name| Direction::parse(name.trim()).ok_or(format!("unknown direction '{}'", name)))
+            .collect::<Result<_, _>>()?;
+    }
+    Ok(options)
+}
+
+fn report_search(grid: &[Vec<char>], words: &str, options: &SearchOptions) {
+    let words: Vec<&str> = words.split(',').map(str::trim).filter(|w| !w.is_empty()).collect();
+    let matches = search(grid, &words, options);
+    for word in &words {
+        println!("{}: {}", word, matches.iter().filter(|m| m.word == *word).count());
+    }
+    if utils::has_flag("--matches") {
+        for m in &matches {
+            println!("  {} at ({}, {}) going {:?}", m.word, m.start.0, m.start.1, m.direction);
+        }
+    }
+    if utils::has_flag("--highlight") {
+        for line in highlight(grid, &matches, options.wrap) {
+            println!("{}", line);
+        }
     }
 }
\ No newline at end of file
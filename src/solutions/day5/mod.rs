@@ -1,10 +1,119 @@
 use crate::utils;
+use std::cmp::Ordering;
 use std::collections::{HashMap, VecDeque};
+use std::fmt;
 
-fn part1(graph: &HashMap<i32, Vec<i32>>, second_part_array: &Vec<Vec<i32>>) -> i32 {
+/// The page ordering rules as a bitset matrix: bit `(a, b)` is set for every rule `a|b`,
+/// so "must `a` come before `b`?" is a single lookup. Pages are numbered densely in
+/// order of first appearance, so the matrix grows with the number of distinct pages
+/// rather than with the largest page number.
+pub struct PageOrder {
+    index: HashMap<i32, usize>,
+    words_per_row: usize,
+    bits: Vec<u64>,
+}
+
+impl PageOrder {
+    pub fn from_rules(rules: &[(i32, i32)]) -> Self {
+        let mut index = HashMap::new();
+        for &(a, b) in rules {
+            for page in [a, b] {
+                let next = index.len();
+                index.entry(page).or_insert(next);
+            }
+        }
+        let size = index.len();
+        let words_per_row = size.div_ceil(64);
+        let mut order = PageOrder { index, words_per_row, bits: vec![0; size * words_per_row] };
+        for &(a, b) in rules {
+            let (a, b) = (order.index[&a], order.index[&b]);
+            order.bits[a * words_per_row + b / 64] |= 1 << (b % 64);
+        }
+        order
+    }
+
+    /// True if a rule says `a` must be printed before `b`.
+    pub fn before(&self, a: i32, b: i32) -> bool {
+        match (self.index.get(&a), self.index.get(&b)) {
+            (Some(&a), Some(&b)) => self.bits[a * self.words_per_row + b / 64] & (1 << (b % 64)) != 0,
+            _ => false,
+        }
+    }
+
+    /// Comparator for `sort_by`. Pages no rule relates compare as `Equal`, so only
+    /// use it once `check_total` has accepted the update.
+    pub fn compare(&self, a: i32, b: i32) -> Ordering {
+        if a == b {
+            Ordering::Equal
+        } else if self.before(a, b) {
+            Ordering::Less
+        } else if self.before(b, a) {
+            Ordering::Greater
+        } else {
+            Ordering::Equal
+        }
+    }
+
+    /// Checks that the rules order the pages of `arr` completely and consistently.
+    ///
+    /// With exactly one rule between every pair, the rules form a tournament, which is
+    /// a total order exactly when the pages precede 0, 1, ..., n-1 others respectively.
+    pub fn check_total(&self, arr: &[i32]) -> Result<(), Ambiguity> {
+        let mut unordered = Vec::new();
+        let mut contradictory = Vec::new();
+        let mut precedes = vec![0; arr.len()];
+        for (i, &a) in arr.iter().enumerate() {
+            for (j, &b) in arr.iter().enumerate().skip(i + 1) {
+                match (self.before(a, b), self.before(b, a)) {
+                    (true, false) => precedes[i] += 1,
+                    (false, true) => precedes[j] += 1,
+                    (false, false) => unordered.push((a, b)),
+                    (true, true) => contradictory.push((a, b)),
+                }
+            }
+        }
+        if !contradictory.is_empty() {
+            return Err(Ambiguity::Contradictory(contradictory));
+        }
+        if !unordered.is_empty() {
+            return Err(Ambiguity::Unordered(unordered));
+        }
+        precedes.sort_unstable();
+        if precedes.iter().enumerate().any(|(rank, &count)| rank != count) {
+            return Err(Ambiguity::Cyclic);
+        }
+        Ok(())
+    }
+}
+
+/// Why the rules do not determine a single order for an update.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Ambiguity {
+    /// Pairs of pages that no rule relates.
+    Unordered(Vec<(i32, i32)>),
+    /// Pairs of pages with rules in both directions.
+    Contradictory(Vec<(i32, i32)>),
+    /// Every pair is related, but the rules go round in a circle.
+    Cyclic,
+}
+
+impl fmt::Display for Ambiguity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let pairs = |pairs: &[(i32, i32)]| {
+            pairs.iter().map(|(a, b)| format!("{}/{}", a, b)).collect::<Vec<_>>().join(", ")
+        };
+        match self {
+            Ambiguity::Unordered(unordered) => write!(f, "no rule directly orders {}", pairs(unordered)),
+            Ambiguity::Contradictory(both) => write!(f, "rules in both directions for {}", pairs(both)),
+            Ambiguity::Cyclic => write!(f, "rules form a cycle"),
+        }
+    }
+}
+
+fn part1(order: &PageOrder, second_part_array: &[Vec<i32>]) -> i32 {
     let mut middle_sum = 0;
     for elements in second_part_array {
-        if elements.windows(2).all(|w| graph.get(&w[0]).map_or(false, |n| n.contains(&w[1]))) {
+        if is_ordered(elements, order) {
             middle_sum += elements[elements.len() / 2]; // Add the middle element value
         }
     }
@@ -12,44 +121,115 @@ fn part1(graph: &HashMap<i32, Vec<i32>>, second_part_array: &Vec<Vec<i32>>) -> i
 }
 
 
-fn topological_sort(arr: &Vec<i32>, graph: &HashMap<i32, Vec<i32>>) -> Option<Vec<i32>> {
-    // Initialize in-degree for each node in the array
-    let mut in_degree: HashMap<i32, usize> = HashMap::new();
-    for &node in arr {
-        in_degree.entry(node).or_insert(0);
+/// Every adjacent pair must be backed by an explicit rule.
+fn is_ordered(arr: &[i32], order: &PageOrder) -> bool {
+    arr.windows(2).all(|w| order.before(w[0], w[1]))
+}
+
+/// Why an update is not in the right order.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Violation {
+    /// The rule `before|after` exists, yet `after` is printed first.
+    Broken { before: i32, after: i32 },
+    /// Adjacent pages that no rule orders either way.
+    Unordered { first: i32, second: i32 },
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Violation::Broken { before, after } => {
+                write!(f, "rule {}|{} broken: {} comes first", before, after, after)
+            }
+            Violation::Unordered { first, second } => {
+                write!(f, "no rule orders {} before {}", first, second)
+            }
+        }
     }
+}
 
-    // Compute in-degree based on the graph's edges
-    for &node in arr {
-        if let Some(neighbors) = graph.get(&node) {
-            for &neighbor in neighbors {
-                if arr.contains(&neighbor) {
-                    *in_degree.entry(neighbor).or_insert(0) += 1;
+/// Lists every rule the update breaks, in the order the offending pages appear.
+/// If no rule is broken but the update is still not ordered, the adjacent pairs
+/// that lack a rule are reported instead.
+pub fn find_violations(arr: &[i32], order: &PageOrder) -> Vec<Violation> {
+    let mut violations = Vec::new();
+    for (i, &earlier) in arr.iter().enumerate() {
+        for &later in &arr[i + 1..] {
+            if order.before(later, earlier) {
+                violations.push(Violation::Broken { before: later, after: earlier });
+            }
+        }
+    }
+    if violations.is_empty() {
+        for w in arr.windows(2) {
+            if !order.before(w[0], w[1]) {
+                violations.push(Violation::Unordered { first: w[0], second: w[1] });
+            }
+        }
+    }
+    violations
+}
+
+/// Shortest cycle of rules among the pages of `arr`, e.g. `[13, 29, 47]` for
+/// `13|29`, `29|47`, `47|13`. Returns `None` if the rules are acyclic on these pages.
+pub fn find_cycle(arr: &[i32], order: &PageOrder) -> Option<Vec<i32>> {
+    let successors = |node: i32| arr.iter().copied().filter(move |&n| order.before(node, n));
+
+    let mut shortest: Option<Vec<i32>> = None;
+    for &start in arr {
+        // Breadth-first search for the shortest path from `start` back to itself.
+        let mut parent: HashMap<i32, i32> = HashMap::new();
+        let mut queue: VecDeque<i32> = VecDeque::from([start]);
+        let mut closing = None;
+        while let Some(node) = queue.pop_front() {
+            if successors(node).any(|n| n == start) {
+                closing = Some(node);
+                break;
+            }
+            for next in successors(node) {
+                if next != start && !parent.contains_key(&next) {
+                    parent.insert(next, node);
+                    queue.push_back(next);
                 }
             }
         }
+        if let Some(mut node) = closing {
+            let mut cycle = vec![node];
+            while node != start {
+                node = parent[&node];
+                cycle.push(node);
+            }
+            cycle.reverse();
+            if shortest.as_ref().is_none_or(|best| cycle.len() < best.len()) {
+                shortest = Some(cycle);
+            }
+        }
     }
+    shortest
+}
 
-    // Queue for nodes with in-degree 0
-    let mut queue: VecDeque<i32> = in_degree
+/// Kahn's algorithm over the pages of `arr`; works for partial orders too.
+fn topological_sort(arr: &[i32], order: &PageOrder) -> Option<Vec<i32>> {
+    // In-degree of each page, counting only rules between pages of this update
+    let mut in_degree: Vec<usize> = arr
         .iter()
-        .filter_map(|(&node, &deg)| if deg == 0 { Some(node) } else { None })
+        .map(|&node| arr.iter().filter(|&&other| order.before(other, node)).count())
         .collect();
 
+    // Queue for nodes with in-degree 0
+    let mut queue: VecDeque<usize> = (0..arr.len()).filter(|&i| in_degree[i] == 0).collect();
+
     let mut sorted = Vec::new();
 
-    while let Some(node) = queue.pop_front() {
+    while let Some(index) = queue.pop_front() {
+        let node = arr[index];
         sorted.push(node);
 
-        if let Some(neighbors) = graph.get(&node) {
-            for &neighbor in neighbors {
-                if arr.contains(&neighbor) {
-                    if let Some(deg) = in_degree.get_mut(&neighbor) {
-                        *deg -= 1;
-                        if *deg == 0 {
-                            queue.push_back(neighbor);
-                        }
-                    }
+        for (neighbor, &page) in arr.iter().enumerate() {
+            if order.before(node, page) {
+                in_degree[neighbor] -= 1;
+                if in_degree[neighbor] == 0 {
+                    queue.push_back(neighbor);
                 }
             }
         }
@@ -63,24 +243,148 @@ fn topological_sort(arr: &Vec<i32>, graph: &HashMap<i32, Vec<i32>>) -> Option<Ve
     }
 }
 
-fn part2(graph: &HashMap<i32, Vec<i32>>, second_part_array: &Vec<Vec<i32>>) -> i32 {
+/// Puts an update into rule order. When the rules define a total order on its pages
+/// this is a plain comparator sort; otherwise the ambiguity is returned alongside
+/// whatever order Kahn's algorithm finds.
+pub fn reorder(arr: &[i32], order: &PageOrder) -> (Option<Vec<i32>>, Option<Ambiguity>) {
+    match order.check_total(arr) {
+        Ok(()) => {
+            let mut sorted = arr.to_vec();
+            sorted.sort_by(|&a, &b| order.compare(a, b));
+            (Some(sorted), None)
+        }
+        Err(ambiguity) => (topological_sort(arr, order), Some(ambiguity)),
+    }
+}
+
+/// Updates up to this many pages are counted with the bitmask DP.
+const MAX_DP_PAGES: usize = 20;
+
+/// How many ways the rules allow an update to be ordered.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OrderingReport {
+    /// Number of orders consistent with the rules; `None` if the update is too
+    /// large to count and the rules leave its order open.
+    pub count: Option<u128>,
+    /// Pages that are in the middle of at least one valid order.
+    pub middle_candidates: Vec<i32>,
+}
+
+impl OrderingReport {
+    pub fn middle_is_unique(&self) -> bool {
+        self.middle_candidates.len() == 1
+    }
+}
+
+/// Counts the linear extensions of the rules on `arr` and works out which pages
+/// can end up in the middle.
+///
+/// Page `p` can be placed anywhere from position `|pages forced before p|` to
+/// `n - 1 - |pages forced after p|` in some valid order, so the middle candidates
+/// follow from the transitive closure alone, whatever the size of the update.
+pub fn analyze_orderings(arr: &[i32], order: &PageOrder) -> OrderingReport {
+    let n = arr.len();
+    // reaches[i][j]: a chain of rules forces arr[i] before arr[j].
+    let mut reaches: Vec<Vec<bool>> = arr
+        .iter()
+        .map(|&a| arr.iter().map(|&b| order.before(a, b)).collect())
+        .collect();
+    for k in 0..n {
+        let via = reaches[k].clone();
+        for row in reaches.iter_mut().filter(|row| row[k]) {
+            for (cell, &through_k) in row.iter_mut().zip(&via) {
+                *cell |= through_k;
+            }
+        }
+    }
+
+    if (0..n).any(|i| reaches[i][i]) {
+        // A cycle admits no valid order at all.
+        return OrderingReport { count: Some(0), middle_candidates: Vec::new() };
+    }
+
+    let middle = n / 2;
+    let middle_candidates = (0..n)
+        .filter(|&p| {
+            let forced_before = (0..n).filter(|&q| reaches[q][p]).count();
+            let forced_after = (0..n).filter(|&q| reaches[p][q]).count();
+            forced_before <= middle && middle < n - forced_after
+        })
+        .map(|p| arr[p])
+        .collect();
+
+    // Chains of rules may order every pair even where no single rule does; then only one order exists.
+    let total = (0..n).all(|i| (0..n).all(|j| i == j || reaches[i][j] || reaches[j][i]));
+    let count = if total {
+        Some(1)
+    } else if n <= MAX_DP_PAGES {
+        let predecessors: Vec<u32> = (0..n)
+            .map(|i| (0..n).filter(|&j| order.before(arr[j], arr[i])).fold(0, |mask, j| mask | 1 << j))
+            .collect();
+        let mut memo = HashMap::new();
+        Some(count_extensions(0, &predecessors, &mut memo))
+    } else {
+        None
+    };
+
+    OrderingReport { count, middle_candidates }
+}
+
+/// Orders of the pages not yet in `placed`, given the pages in `placed` come first.
+fn count_extensions(placed: u32, predecessors: &[u32], memo: &mut HashMap<u32, u128>) -> u128 {
+    let full = (1u32 << predecessors.len()) - 1;
+    if placed == full {
+        return 1;
+    }
+    if let Some(&count) = memo.get(&placed) {
+        return count;
+    }
+    let count = (0..predecessors.len())
+        .filter(|&i| placed & (1 << i) == 0 && predecessors[i] & !placed == 0)
+        .map(|i| count_extensions(placed | 1 << i, predecessors, memo))
+        .sum();
+    memo.insert(placed, count);
+    count
+}
+
+/// Prints a summary of how constrained each update is, listing the updates
+/// whose middle page depends on which valid order is chosen.
+fn report_orderings(order: &PageOrder, second_part_array: &[Vec<i32>]) {
+    let mut unique = 0;
+    for (line, arr) in second_part_array.iter().enumerate() {
+        let report = analyze_orderings(arr, order);
+        if report.count == Some(1) {
+            unique += 1;
+        }
+        if !report.middle_is_unique() {
+            let count = report.count.map_or("too many to count".to_string(), |c| c.to_string());
+            println!(
+                "Update {} {:?}: {} valid orders, middle page could be {:?}",
+                line + 1, arr, count, report.middle_candidates
+            );
+        }
+    }
+    println!("Orderings: {} of {} updates have exactly one valid order", unique, second_part_array.len());
+}
+
+fn part2(order: &PageOrder, second_part_array: &[Vec<i32>]) -> i32 {
     let mut middle_sum = 0;
     for arr in second_part_array {
-        // Check if the array follows the graph's order
-        let is_correct = arr.windows(2).all(|w| {
-            graph
-                .get(&w[0])
-                .map_or(false, |n| n.contains(&w[1]))
-        });
-
-        if !is_correct {
-            // Reorder the array using topological sort
-            if let Some(sorted) = topological_sort(arr, graph) {
+        // Check if the array follows the rules' order
+        if !is_ordered(arr, order) {
+            let (sorted, ambiguity) = reorder(arr, order);
+            if let Some(sorted) = sorted {
+                if let Some(ambiguity) = ambiguity {
+                    println!("Ambiguous order for {:?}: {}", arr, ambiguity);
+                }
                 // println!("Original: {:?} --> Reordered: {:?}", arr, sorted);
                 let middle = sorted[sorted.len() / 2];
                 middle_sum += middle;
             } else {
-                println!("Failed to sort array (possible cycle or incomplete constraints): {:?}", arr);
+                match find_cycle(arr, order) {
+                    Some(cycle) => println!("Failed to sort {:?}: rules form a cycle {}", arr, format_cycle(&cycle)),
+                    None => println!("Failed to sort {:?}: no cycle found", arr),
+                }
             }
         }
     }
@@ -90,17 +394,37 @@ fn part2(graph: &HashMap<i32, Vec<i32>>, second_part_array: &Vec<Vec<i32>>) -> i
 
 
 
-fn get_graph(graph_input: &str) ->HashMap<i32, Vec<i32>>{
-    let mut graph: HashMap<i32, Vec<i32>> = HashMap::new();
+/// Renders a cycle as `13 -> 29 -> 47 -> 13`.
+fn format_cycle(cycle: &[i32]) -> String {
+    cycle
+        .iter()
+        .chain(cycle.first())
+        .map(|page| page.to_string())
+        .collect::<Vec<_>>()
+        .join(" -> ")
+}
 
-    // Build the graph from the input
-    graph_input.lines().for_each(|line| {
-        let mut parts = line.split('|').filter_map(|s| s.trim().parse::<i32>().ok());
-        if let (Some(from), Some(to)) = (parts.next(), parts.next()) {
-            graph.entry(from).or_insert_with(Vec::new).push(to);
+/// Prints why each out-of-order update fails.
+fn explain(order: &PageOrder, second_part_array: &[Vec<i32>]) {
+    for (line, arr) in second_part_array.iter().enumerate() {
+        if is_ordered(arr, order) {
+            continue;
+        }
+        println!("Update {} {:?}:", line + 1, arr);
+        for violation in find_violations(arr, order) {
+            println!("  {}", violation);
         }
-    });
-    graph
+    }
+}
+
+fn get_rules(rules_input: &str) -> Vec<(i32, i32)> {
+    rules_input
+        .lines()
+        .filter_map(|line| {
+            let mut parts = line.split('|').filter_map(|s| s.trim().parse::<i32>().ok());
+            Some((parts.next()?, parts.next()?))
+        })
+        .collect()
 }
 
 fn get_string_array(lines_input: &str) -> Vec<Vec<i32>>{
@@ -120,12 +444,18 @@ pub fn solve(day: u32) {
 
     if let Ok(contents) = utils::read_file(&format!("src/solutions/day{}/input.txt", day)) {
         let parts: Vec<&str> = contents.split("\n\n").collect();
-        let graph = get_graph(parts[0]);
+        let order = PageOrder::from_rules(&get_rules(parts[0]));
         let second_part_array = get_string_array(parts[1]);
 
-        let part1 = part1(&graph,&second_part_array) ; 
-        let part2 = part2(&graph,&second_part_array);
+        let part1 = part1(&order, &second_part_array);
+        let part2 = part2(&order, &second_part_array);
         println!("Part1:  {}", part1);
         println!("Part2:  {:?}", part2);
+        if utils::has_flag("--explain") {
+            explain(&order, &second_part_array);
+        }
+        if utils::has_flag("--count-orderings") {
+            report_orderings(&order, &second_part_array);
+        }
     }
-}
\ No newline at end of file
+}
@@ -1,9 +1,10 @@
 use crate::utils;
 use std::collections::HashSet;
+use std::io::Write;
 
 type Grid = Vec<Vec<char>>;
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 enum Direction {
     North,
     East,
@@ -22,6 +23,35 @@ impl Direction {
         }
     }
 
+    /// 左に90度回転
+    fn turn_left(&self) -> Direction {
+        self.turn_right().turn_right().turn_right()
+    }
+
+    fn reverse(&self) -> Direction {
+        self.turn_right().turn_right()
+    }
+
+    fn from_marker(marker: char) -> Option<Direction> {
+        match marker {
+            '^' => Some(Direction::North),
+            '>' => Some(Direction::East),
+            'v' => Some(Direction::South),
+            '<' => Some(Direction::West),
+            _   => None,
+        }
+    }
+
+    /// Index into per-direction tables.
+    fn index(self) -> usize {
+        match self {
+            Direction::North => 0,
+            Direction::East  => 1,
+            Direction::South => 2,
+            Direction::West  => 3,
+        }
+    }
+
     /// 現在の方向に基づいて移動のデルタを返す
     fn move_delta(&self) -> (isize, isize) {
         match self {
@@ -33,9 +63,84 @@ impl Direction {
     }
 }
 
+/// What the guard does when the next cell is blocked.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Turn {
+    Right,
+    Left,
+    Reverse,
+}
+
+impl Turn {
+    fn apply(self, dir: Direction) -> Direction {
+        match self {
+            Turn::Right   => dir.turn_right(),
+            Turn::Left    => dir.turn_left(),
+            Turn::Reverse => dir.reverse(),
+        }
+    }
+}
+
+/// How guards move around the map.
+#[derive(Debug, Clone)]
+struct GuardRules {
+    turn: Turn,
+    /// Characters that block the guard. `None` blocks everything except
+    /// floor (`.`) and guard markers, as in the puzzle.
+    obstacles: Option<Vec<char>>,
+    /// Walking off one edge re-enters on the opposite edge instead of leaving.
+    wrap: bool,
+}
+
+impl Default for GuardRules {
+    fn default() -> Self {
+        GuardRules { turn: Turn::Right, obstacles: None, wrap: false }
+    }
+}
+
+impl GuardRules {
+    /// Reads `--turn right|left|reverse`, `--obstacles <chars>` and `--wrap`.
+    fn from_args() -> Result<Self, String> {
+        let mut rules = GuardRules { wrap: utils::has_flag("--wrap"), ..GuardRules::default() };
+        if let Some(turn) = utils::flag_value("--turn") {
+            rules.turn = match turn.as_str() {
+                "right" => Turn::Right,
+                "left" => Turn::Left,
+                "reverse" => Turn::Reverse,
+                _ => return Err(format!("invalid --turn '{}' (right, left or reverse)", turn)),
+            };
+        }
+        if let Some(chars) = utils::flag_value("--obstacles") {
+            rules.obstacles = Some(chars.chars().collect());
+        }
+        Ok(rules)
+    }
+
+    fn blocks(&self, cell: char) -> bool {
+        match &self.obstacles {
+            Some(obstacles) => obstacles.contains(&cell),
+            None => !matches!(cell, '.' | '^' | '>' | 'v' | '<'),
+        }
+    }
+
+    /// The cell one step ahead, or `None` if that step leaves the map.
+    fn ahead(&self, grid: &Grid, pos: (usize, usize), dir: Direction) -> Option<(usize, usize)> {
+        let (rows, cols) = (grid.len() as isize, grid[0].len() as isize);
+        let (dx, dy) = dir.move_delta();
+        let (mut x, mut y) = (pos.0 as isize + dx, pos.1 as isize + dy);
+        if self.wrap {
+            x = x.rem_euclid(rows);
+            y = y.rem_euclid(cols);
+        } else if x < 0 || x >= rows || y < 0 || y >= cols {
+            return None;
+        }
+        Some((x as usize, y as usize))
+    }
+}
+
 fn part1(grid: &Grid) -> usize {
     // 開始位置と方向の特定
-    let (start_pos, start_dir) = match find_start(&grid) {
+    let (start_pos, start_dir) = match find_start(grid) {
         Some((pos, dir)) => (pos, dir),
         None => {
             println!("開始位置 '^' がグリッド内に見つかりません。");
@@ -44,151 +149,444 @@ fn part1(grid: &Grid) -> usize {
     };
 
     // 移動のシミュレーション
-    let max_steps = 90000; // 安全策として最大ステップ数を設定
-    let  (visited, _reached_edge)  = simulate_movement(&grid, start_pos, start_dir, max_steps);
-    visited.len()
+    let walk = simulate_movement(grid, &GuardRules::default(), start_pos, start_dir);
+    walk.visited().len()
 }
 
 
 
 
-fn part2(grid: &Grid) -> usize {
+/// Returns the obstruction positions themselves so the renderers can reuse them.
+fn part2(grid: &Grid) -> Vec<(usize, usize)> {
     // 開始位置と方向の特定
-    let (start_pos, start_dir) = match find_start(&grid) {
+    let (start_pos, start_dir) = match find_start(grid) {
         Some((pos, dir)) => (pos, dir),
         None => {
             println!("開始位置 '^' がグリッド内に見つかりません。");
-            return 0;
+            return Vec::new();
         }
     };
 
-    let problematic_positions = find_problematic_positions(&grid, start_pos, start_dir);
-    problematic_positions.len()
+    find_problematic_positions(grid, start_pos, start_dir)
 }
 
 
 fn find_start(grid: &Grid) -> Option<((usize, usize), Direction)> {
+    find_guards(grid).into_iter().next()
+}
+
+/// Every guard marker on the map, in reading order.
+fn find_guards(grid: &Grid) -> Vec<State> {
+    let mut guards = Vec::new();
     for (i, row) in grid.iter().enumerate() {
         for (j, &cell) in row.iter().enumerate() {
-            let direction = match cell {
-                '^' => Direction::North,
-                '>' => Direction::East,
-                'v' => Direction::South,
-                '<' => Direction::West,
-                _   => continue,
-            };
-            return Some(((i, j), direction));
+            if let Some(direction) = Direction::from_marker(cell) {
+                guards.push(((i, j), direction));
+            }
         }
     }
-    None
+    guards
 }
 
 
+/// Obstacle test under the puzzle's default rules.
 fn is_obstacle(grid: &Grid, pos: (usize, usize)) -> bool {
     let (x, y) = pos;
     if x >= grid.len() || y >= grid[0].len() {
         return true; // グリッド外は障害物とみなす
     }
-    match grid[x][y] {
-        '.' | '^' | '>' | 'v' | '<' => false, // 移動可能なセル
-        _ => true, // その他は障害物
-    }
+    GuardRules::default().blocks(grid[x][y])
 }
 
-/// ガードがグリッドのエッジに到達したかどうかを判定する関数
-fn is_on_edge(grid: &Grid, pos: (usize, usize)) -> bool {
-    let (x, y) = pos;
-    x == 0 || y == 0 || x == grid.len() - 1 || y == grid[0].len() - 1
+/// How a walk ends.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Outcome {
+    /// The guard walked off the map after `steps` steps.
+    Exited { steps: usize },
+    /// The guard is back in the (position, direction) state it had after
+    /// `cycle_start` steps and will repeat the same `cycle_len` steps forever.
+    Looped { cycle_start: usize, cycle_len: usize },
+}
+
+/// A guard's route and how it ended.
+struct Walk {
+    /// The state before every step, in order.
+    route: Vec<State>,
+    outcome: Outcome,
 }
 
+impl Walk {
+    fn visited(&self) -> HashSet<(usize, usize)> {
+        self.route.iter().map(|&(pos, _)| pos).collect()
+    }
+}
+
+/// Walks the guard until it leaves the map or repeats a (position, direction) state.
+/// Each move forward and each turn counts as one step.
 fn simulate_movement(
     grid: &Grid,
+    rules: &GuardRules,
     start_pos: (usize, usize),
     start_dir: Direction,
-    max_steps: usize,
-) -> (HashSet<(usize, usize)>, bool){
-    let mut visited: HashSet<(usize, usize)> = HashSet::new();
+) -> Walk {
+    let cols = grid[0].len();
+    // Step at which each (position, direction) state was first reached.
+    let mut seen: Vec<Option<usize>> = vec![None; grid.len() * cols * 4];
+    let state_index = |pos: (usize, usize), dir: Direction| (pos.0 * cols + pos.1) * 4 + dir.index();
+
+    let mut route: Vec<State> = Vec::new();
     let mut current_pos = start_pos;
     let mut current_dir = start_dir;
-    visited.insert(current_pos);
 
-    let mut loop_detected = false;
-
-    for _step in 0..max_steps {
-        // 現在の方向に基づいて次の位置を計算
-        let (dx, dy) = current_dir.move_delta();
-        let new_x = current_pos.0 as isize + dx;
-        let new_y = current_pos.1 as isize + dy;
-
-        // 新しい位置がグリッド外に出る場合、終了
-        if new_x < 0 || new_x >= grid.len() as isize || new_y < 0 || new_y >= grid[0].len() as isize {
-            println!("ガードがグリッドのエッジに到達しました: ({}, {})", current_pos.0, current_pos.1);
-            loop_detected = false;
-            break;
+    for step in 0.. {
+        let state = state_index(current_pos, current_dir);
+        if let Some(first) = seen[state] {
+            return Walk { route, outcome: Outcome::Looped { cycle_start: first, cycle_len: step - first } };
         }
+        seen[state] = Some(step);
+        route.push((current_pos, current_dir));
 
-        let new_pos = (new_x as usize, new_y as usize);
+        // 現在の方向に基づいて次の位置を計算。グリッド外に出る場合、終了
+        let Some(new_pos) = rules.ahead(grid, current_pos, current_dir) else {
+            return Walk { route, outcome: Outcome::Exited { steps: step } };
+        };
 
-        if is_obstacle(grid, new_pos) {
-            // 障害物がある場合は右に90度回転
-            current_dir = current_dir.turn_right();
+        if rules.blocks(grid[new_pos.0][new_pos.1]) {
+            // 障害物がある場合は回転
+            current_dir = rules.turn.apply(current_dir);
         } else {
             // 障害物がない場合は前進
             current_pos = new_pos;
-            visited.insert(current_pos);
+        }
+    }
+    unreachable!("the number of states is finite")
+}
+
+/// Walks every guard on the map and reports its route and where routes cross.
+fn report_guards(grid: &Grid, rules: &GuardRules) {
+    let guards = find_guards(grid);
+    let visited: Vec<HashSet<(usize, usize)>> = guards
+        .iter()
+        .enumerate()
+        .map(|(i, &(pos, dir))| {
+            let walk = simulate_movement(grid, rules, pos, dir);
+            let visited = walk.visited();
+            let ending = match walk.outcome {
+                Outcome::Exited { steps } => format!("leaves the map after {} steps", steps),
+                Outcome::Looped { cycle_start, cycle_len } => {
+                    format!("loops from step {} every {} steps", cycle_start, cycle_len)
+                }
+            };
+            println!("Guard {} at {:?} facing {:?}: {} cells, {}", i + 1, pos, dir, visited.len(), ending);
+            visited
+        })
+        .collect();
+
+    for i in 0..guards.len() {
+        for j in i + 1..guards.len() {
+            let shared = visited[i].intersection(&visited[j]).count();
+            if shared > 0 {
+                println!("Guards {} and {} share {} cells", i + 1, j + 1, shared);
+            } else {
+                println!("Guards {} and {} never cross", i + 1, j + 1);
+            }
+        }
+    }
+}
+
+/// A guard's position and heading.
+type State = ((usize, usize), Direction);
+
+/// For every direction and walkable cell, where the guard stops when walking that
+/// way: the cell in front of the next obstacle, or `None` if it walks off the map.
+struct JumpTable {
+    rows: usize,
+    cols: usize,
+    stops: [Vec<Option<usize>>; 4],
+}
 
-            // 移動後の位置がエッジなら終了
-            if is_on_edge(grid, current_pos) {
-                // println!("ガードがグリッドのエッジに到達しました: ({}, {})", current_pos.0, current_pos.1);
-                loop_detected = false;
-                break;
+impl JumpTable {
+    fn new(grid: &Grid) -> JumpTable {
+        let (rows, cols) = (grid.len(), grid[0].len());
+        let mut stops: [Vec<Option<usize>>; 4] = std::array::from_fn(|_| vec![None; rows * cols]);
+        for dir in [Direction::North, Direction::East, Direction::South, Direction::West] {
+            let (dx, dy) = dir.move_delta();
+            // Visit cells so that the next cell in `dir` has already been filled in.
+            let row_order: Vec<usize> = if dx > 0 { (0..rows).rev().collect() } else { (0..rows).collect() };
+            let col_order: Vec<usize> = if dy > 0 { (0..cols).rev().collect() } else { (0..cols).collect() };
+            for &x in &row_order {
+                for &y in &col_order {
+                    let (nx, ny) = (x as isize + dx, y as isize + dy);
+                    let stop = if nx < 0 || nx >= rows as isize || ny < 0 || ny >= cols as isize {
+                        None
+                    } else if is_obstacle(grid, (nx as usize, ny as usize)) {
+                        Some(x * cols + y)
+                    } else {
+                        stops[dir.index()][nx as usize * cols + ny as usize]
+                    };
+                    stops[dir.index()][x * cols + y] = stop;
+                }
             }
-            // Check for loop by position and direction
-            let state = current_pos;
-            if !visited.insert(state) {
-                loop_detected = true; // Revisited state, infinite loop detected
-            }            
         }
+        JumpTable { rows, cols, stops }
     }
 
-    (visited, loop_detected)
+    /// Walks from `pos` in `dir` as far as possible, also treating `extra` as an obstacle.
+    fn jump(&self, pos: (usize, usize), dir: Direction, extra: (usize, usize)) -> Option<(usize, usize)> {
+        let stop = self.stops[dir.index()][pos.0 * self.cols + pos.1].map(|i| (i / self.cols, i % self.cols));
+        // Distance ahead of `pos` to `extra`, if it lies on the line being walked.
+        let ahead = match dir {
+            Direction::North if extra.1 == pos.1 && extra.0 < pos.0 => Some(pos.0 - extra.0),
+            Direction::South if extra.1 == pos.1 && extra.0 > pos.0 => Some(extra.0 - pos.0),
+            Direction::West if extra.0 == pos.0 && extra.1 < pos.1 => Some(pos.1 - extra.1),
+            Direction::East if extra.0 == pos.0 && extra.1 > pos.1 => Some(extra.1 - pos.1),
+            _ => None,
+        };
+        let Some(ahead) = ahead else { return stop };
+        let reach = match stop {
+            Some(stop) => stop.0.abs_diff(pos.0) + stop.1.abs_diff(pos.1),
+            None => self.rows.max(self.cols),
+        };
+        if ahead <= reach {
+            let (dx, dy) = dir.move_delta();
+            let back = ahead as isize - 1;
+            Some(((pos.0 as isize + dx * back) as usize, (pos.1 as isize + dy * back) as usize))
+        } else {
+            stop
+        }
+    }
+
+    /// True if the guard, starting in the given state, loops once `extra` is an obstacle.
+    /// Only the states right after a turn are recorded; `seen` holds the `stamp` of the
+    /// last search that reached each of them, so it can be reused without clearing.
+    fn loops_with(&self, start: State, extra: (usize, usize), seen: &mut [u32], stamp: u32) -> bool {
+        let (mut pos, mut dir) = start;
+        loop {
+            match self.jump(pos, dir, extra) {
+                None => return false,
+                Some(stop) => pos = stop,
+            }
+            dir = dir.turn_right();
+            let state = (pos.0 * self.cols + pos.1) * 4 + dir.index();
+            if seen[state] == stamp {
+                return true;
+            }
+            seen[state] = stamp;
+        }
+    }
 }
 
 /// List all positions where placing an obstacle causes an infinite loop
+///
+/// Only cells on the original route can change it. Each candidate is tested by
+/// resuming the walk from the state just before the guard first reaches it, using
+/// jump tables to skip straight to the next turn; candidates are split across threads.
 fn find_problematic_positions(grid: &Grid, start_pos: (usize, usize), start_dir: Direction) -> Vec<(usize, usize)> {
-    let mut problematic = Vec::new();
+    let cols = grid[0].len();
+
+    // First time the guard steps onto each cell, with the state it steps from.
+    let mut candidates: Vec<((usize, usize), State)> = Vec::new();
+    let mut reached = vec![false; grid.len() * cols];
+    reached[start_pos.0 * cols + start_pos.1] = true;
+    let mut walked = vec![false; grid.len() * cols * 4];
+    let (mut pos, mut dir) = (start_pos, start_dir);
+    let mut already_loops = false;
+    loop {
+        let state = (pos.0 * cols + pos.1) * 4 + dir.index();
+        if walked[state] {
+            already_loops = true;
+            break;
+        }
+        walked[state] = true;
+        let (dx, dy) = dir.move_delta();
+        let (nx, ny) = (pos.0 as isize + dx, pos.1 as isize + dy);
+        if nx < 0 || nx >= grid.len() as isize || ny < 0 || ny >= cols as isize {
+            break;
+        }
+        let next = (nx as usize, ny as usize);
+        if is_obstacle(grid, next) {
+            dir = dir.turn_right();
+            continue;
+        }
+        if !reached[next.0 * cols + next.1] {
+            reached[next.0 * cols + next.1] = true;
+            candidates.push((next, (pos, dir)));
+        }
+        pos = next;
+    }
 
-    for i in 0..grid.len() {
-        for j in 0..grid[0].len() {
-            // Skip if the cell is already an obstacle or the start position
-            if is_obstacle(grid, (i, j)) || (i, j) == start_pos {
-                continue;
+    let jumps = JumpTable::new(grid);
+    let threads = std::thread::available_parallelism().map_or(1, |n| n.get());
+    let chunk_size = candidates.len().div_ceil(threads).max(1);
+    let mut problematic: Vec<(usize, usize)> = std::thread::scope(|scope| {
+        let workers: Vec<_> = candidates
+            .chunks(chunk_size)
+            .map(|chunk| {
+                let jumps = &jumps;
+                scope.spawn(move || {
+                    let mut seen = vec![0u32; jumps.rows * jumps.cols * 4];
+                    chunk
+                        .iter()
+                        .enumerate()
+                        .filter(|(i, (cell, state))| jumps.loops_with(*state, *cell, &mut seen, *i as u32 + 1))
+                        .map(|(_, (cell, _))| *cell)
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect();
+        workers.into_iter().flat_map(|worker| worker.join().expect("worker thread panicked")).collect()
+    });
+
+    if already_loops {
+        // Blocking a cell the guard never reaches leaves the existing loop intact.
+        for i in 0..grid.len() {
+            for j in 0..cols {
+                if !reached[i * cols + j] && !is_obstacle(grid, (i, j)) {
+                    problematic.push((i, j));
+                }
             }
+        }
+    }
+
+    problematic.sort();
+    problematic
+}
 
-            // Create a modified grid with an obstacle at (i, j)
-            let mut modified_grid = grid.clone();
-            modified_grid[i][j] = '#';
+/// Which ways the guard crossed each cell along `route`.
+fn crossings(grid: &Grid, route: &[State]) -> Vec<Vec<(bool, bool)>> {
+    let mut crossed = vec![vec![(false, false); grid[0].len()]; grid.len()];
+    for &(pos, dir) in route {
+        let cell = &mut crossed[pos.0][pos.1];
+        match dir {
+            Direction::North | Direction::South => cell.0 = true,
+            Direction::East | Direction::West => cell.1 = true,
+        }
+    }
+    crossed
+}
 
-            // Simulate movement
-            let _max_steps = 10000;
-            let (_visited, loop_detected)= simulate_movement(&modified_grid, start_pos, start_dir,_max_steps);
-            if loop_detected{
-                // If infinite loop detected, record the position
-                problematic.push((i, j));
+/// The map with the guard's route drawn as in the puzzle text: `|` for vertical
+/// movement, `-` for horizontal, `+` where both happen (including turns), and `O`
+/// for cells where a new obstruction would trap the guard.
+fn render_route(grid: &Grid, route: &[State], obstructions: &[(usize, usize)]) -> Vec<String> {
+    let crossed = crossings(grid, route);
+    let start = route.first().map(|&(pos, _)| pos);
+    let mut canvas: Grid = grid.clone();
+    for (i, row) in canvas.iter_mut().enumerate() {
+        for (j, cell) in row.iter_mut().enumerate() {
+            if Some((i, j)) == start {
+                continue;
             }
+            *cell = match crossed[i][j] {
+                (true, true) => '+',
+                (true, false) => '|',
+                (false, true) => '-',
+                (false, false) => *cell,
+            };
         }
     }
+    for &(i, j) in obstructions {
+        canvas[i][j] = 'O';
+    }
+    canvas.iter().map(|row| row.iter().collect()).collect()
+}
 
-    problematic
+/// Pixels per map cell in PPM output.
+const PPM_SCALE: usize = 4;
+
+const FLOOR_COLOR: [u8; 3] = [20, 20, 30];
+const OBSTACLE_COLOR: [u8; 3] = [200, 200, 200];
+const ROUTE_COLOR: [u8; 3] = [60, 140, 255];
+const GUARD_COLOR: [u8; 3] = [80, 220, 80];
+const OBSTRUCTION_COLOR: [u8; 3] = [230, 60, 60];
+
+/// Writes the map with the first `route.len()` steps of the walk as a binary PPM.
+/// The guard's current cell is highlighted.
+fn write_ppm(path: &str, grid: &Grid, route: &[State], obstructions: &[(usize, usize)]) -> std::io::Result<()> {
+    let (rows, cols) = (grid.len(), grid[0].len());
+    let crossed = crossings(grid, route);
+    let guard = route.last().map(|&(pos, _)| pos);
+    let mut obstructed = vec![vec![false; cols]; rows];
+    for &(r, c) in obstructions {
+        obstructed[r][c] = true;
+    }
+
+    let mut pixels = Vec::with_capacity(rows * cols * PPM_SCALE * PPM_SCALE * 3);
+    for i in 0..rows * PPM_SCALE {
+        for j in 0..cols * PPM_SCALE {
+            let (r, c) = (i / PPM_SCALE, j / PPM_SCALE);
+            let color = if Some((r, c)) == guard {
+                GUARD_COLOR
+            } else if obstructed[r][c] {
+                OBSTRUCTION_COLOR
+            } else if is_obstacle(grid, (r, c)) {
+                OBSTACLE_COLOR
+            } else if crossed[r][c] != (false, false) {
+                ROUTE_COLOR
+            } else {
+                FLOOR_COLOR
+            };
+            pixels.extend_from_slice(&color);
+        }
+    }
+
+    let mut file = std::io::BufWriter::new(std::fs::File::create(path)?);
+    write!(file, "P6\n{} {}\n255\n", cols * PPM_SCALE, rows * PPM_SCALE)?;
+    file.write_all(&pixels)?;
+    file.flush()
 }
 
-/// グリッドを表示する関数（オプション）
-// fn display_grid(grid: &Grid) {
-//     for row in grid {
-//         let line: String = row.iter().collect();
-//         println!("{}", line);
-//     }
-// }
+/// Writes one PPM per `every` steps of the walk (plus the final state) into `dir`.
+fn write_frames(dir: &str, grid: &Grid, route: &[State], every: usize) -> std::io::Result<usize> {
+    std::fs::create_dir_all(dir)?;
+    let mut ends: Vec<usize> = (1..=route.len()).step_by(every.max(1)).collect();
+    if ends.last() != Some(&route.len()) {
+        ends.push(route.len());
+    }
+    for (frame, &end) in ends.iter().enumerate() {
+        write_ppm(&format!("{}/frame_{:05}.ppm", dir, frame), grid, &route[..end], &[])?;
+    }
+    Ok(ends.len())
+}
+
+/// Handles `--render`, `--ppm <file>` and `--frames <dir>` (with `--frame-every <n>`), given part 2's obstructions.
+fn render(grid: &Grid, obstructions: &[(usize, usize)]) {
+    let ppm = utils::flag_value("--ppm");
+    let frames = utils::flag_value("--frames");
+    if !utils::has_flag("--render") && ppm.is_none() && frames.is_none() {
+        return;
+    }
+    let Some((start_pos, start_dir)) = find_start(grid) else { return };
+    let walk = simulate_movement(grid, &GuardRules::default(), start_pos, start_dir);
+
+    if utils::has_flag("--render") {
+        for line in render_route(grid, &walk.route, obstructions) {
+            println!("{}", line);
+        }
+    }
+    if let Some(path) = ppm {
+        match write_ppm(&path, grid, &walk.route, obstructions) {
+            Ok(()) => println!("Wrote {}", path),
+            Err(e) => println!("Failed to write {}: {}", path, e),
+        }
+    }
+    if let Some(dir) = frames {
+        let every = match utils::flag_value("--frame-every") {
+            None => 100,
+            Some(n) => match n.parse::<usize>() {
+                Ok(every) if every > 0 => every,
+                _ => {
+                    println!("invalid --frame-every '{}' (expects a positive integer)", n);
+                    return;
+                }
+            },
+        };
+        match write_frames(&dir, grid, &walk.route, every) {
+            Ok(count) => println!("Wrote {} frames to {}", count, dir),
+            Err(e) => println!("Failed to write frames to {}: {}", dir, e),
+        }
+    }
+}
 
 fn parse_grid(input: &str) -> Grid {
     input
@@ -205,6 +603,13 @@ pub fn solve(day: u32) {
         let part1 = part1(&grid) ; 
         let part2 = part2(&grid);
         println!("Part1:  {}", part1);
-        println!("Part2:  {:?}", part2);
+        println!("Part2:  {:?}", part2.len());
+        render(&grid, &part2);
+        if utils::has_flag("--guards") {
+            match GuardRules::from_args() {
+                Ok(rules) => report_guards(&grid, &rules),
+                Err(e) => println!("{}", e),
+            }
+        }
     }
 }
\ No newline at end of file
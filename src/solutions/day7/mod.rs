@@ -1,225 +1,1356 @@
+use std::cell::Cell;
+use std::collections::HashMap;
 use std::error::Error;
+use std::fmt;
 
 use crate::utils;
 
+use value::Value;
+
+/// Calibration values as `u128`; every operation is checked so overflow is reported, not wrapped.
+#[cfg(not(feature = "bigint"))]
+mod value {
+    pub type Value = u128;
+
+    /// How the value type is named in error messages.
+    pub const NAME: &str = "u128";
+
+    pub fn parse(text: &str) -> Option<Value> {
+        text.parse().ok()
+    }
+
+    pub fn is_zero(n: &Value) -> bool {
+        *n == 0
+    }
+
+    pub fn checked_add(left: &Value, right: &Value) -> Option<Value> {
+        left.checked_add(*right)
+    }
+
+    pub fn checked_mul(left: &Value, right: &Value) -> Option<Value> {
+        left.checked_mul(*right)
+    }
+
+    pub fn saturating_add(left: &Value, right: &Value) -> Value {
+        left.saturating_add(*right)
+    }
+
+    pub fn saturating_mul(left: &Value, right: &Value) -> Value {
+        left.saturating_mul(*right)
+    }
+
+    // The arithmetic below takes references so callers can share one body with `BigUint`
+    // without writing `&local op &local`, which is needless (and linted) for `u128`.
+
+    /// `(n / d, n % d)`; `d` must not be zero.
+    pub fn div_rem(n: &Value, d: &Value) -> (Value, Value) {
+        (n / d, n % d)
+    }
+
+    pub fn xor(left: &Value, right: &Value) -> Value {
+        left ^ right
+    }
+
+    /// The aligned block of `size` values (a power of two) that holds `n`, inclusive.
+    pub fn block(n: &Value, size: &Value) -> (Value, Value) {
+        let first = n / size * size;
+        (first, first + (size - 1))
+    }
+
+    pub fn pow10(exp: u32) -> Option<Value> {
+        10u128.checked_pow(exp)
+    }
+
+    /// Number of decimal digits, counting `0` as one digit.
+    pub fn digits(n: &Value) -> u32 {
+        let mut n = *n;
+        let mut count = 1;
+        while n >= 10 {
+            n /= 10;
+            count += 1;
+        }
+        count
+    }
+}
+
+/// Arbitrary-precision calibration values, enabled with `--features bigint`. Nothing overflows.
+#[cfg(feature = "bigint")]
+mod value {
+    pub type Value = num_bigint::BigUint;
+
+    /// How the value type is named in error messages.
+    pub const NAME: &str = "BigUint";
+
+    pub fn parse(text: &str) -> Option<Value> {
+        text.parse().ok()
+    }
+
+    pub fn is_zero(n: &Value) -> bool {
+        n.bits() == 0
+    }
+
+    pub fn checked_add(left: &Value, right: &Value) -> Option<Value> {
+        Some(left + right)
+    }
+
+    pub fn checked_mul(left: &Value, right: &Value) -> Option<Value> {
+        Some(left * right)
+    }
+
+    pub fn saturating_add(left: &Value, right: &Value) -> Value {
+        left + right
+    }
+
+    pub fn saturating_mul(left: &Value, right: &Value) -> Value {
+        left * right
+    }
+
+    /// `(n / d, n % d)`; `d` must not be zero.
+    pub fn div_rem(n: &Value, d: &Value) -> (Value, Value) {
+        (n / d, n % d)
+    }
+
+    pub fn xor(left: &Value, right: &Value) -> Value {
+        left ^ right
+    }
+
+    /// The aligned block of `size` values (a power of two) that holds `n`, inclusive.
+    pub fn block(n: &Value, size: &Value) -> (Value, Value) {
+        let first = n / size * size;
+        let last = &first + size - 1u8;
+        (first, last)
+    }
+
+    pub fn pow10(exp: u32) -> Option<Value> {
+        Some(Value::from(10u8).pow(exp))
+    }
+
+    /// Number of decimal digits, counting `0` as one digit.
+    pub fn digits(n: &Value) -> u32 {
+        n.to_str_radix(10).len() as u32
+    }
+}
+
 #[derive(Debug)]
 struct Entry {
-    prefix: u128,
-    values: Vec<u128>,
+    /// 1-based line in the input, for error messages.
+    line: usize,
+    prefix: Value,
+    values: Vec<Value>,
+}
+
+/// Why the calibration total could not be computed.
+#[derive(Debug)]
+enum CalibrationError {
+    /// A line is not of the form `target: value value ...`.
+    Malformed { line: usize },
+    /// A number on the line is not a non-negative integer.
+    InvalidNumber { line: usize, text: String },
+    /// A number on the line does not fit in `Value`.
+    TooLarge { line: usize, text: String },
+    /// Evaluating the equations on these lines overflowed before they could be decided.
+    Overflow { lines: Vec<usize> },
+    /// The sum of the solvable targets does not fit in `Value`.
+    TotalOverflow,
+}
+
+impl fmt::Display for CalibrationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CalibrationError::Malformed { line } => write!(f, "line {} is not properly formatted", line),
+            CalibrationError::InvalidNumber { line, text } => write!(f, "line {}: '{}' is not a number", line, text),
+            CalibrationError::TooLarge { line, text } => {
+                write!(f, "line {}: {} does not fit in {} (build with --features bigint)", line, text, value::NAME)
+            }
+            CalibrationError::Overflow { lines } => {
+                let lines: Vec<String> = lines.iter().map(usize::to_string).collect();
+                let plural = if lines.len() == 1 { " on line" } else { "s on lines" };
+                write!(
+                    f,
+                    "equation{} {} overflowed {} (build with --features bigint)",
+                    plural, lines.join(", "), value::NAME
+                )
+            }
+            CalibrationError::TotalOverflow => write!(f, "the calibration total overflowed {}", value::NAME),
+        }
+    }
+}
+
+impl Error for CalibrationError {}
+
+/// Why an operator has no result.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Fault {
+    /// The result is not defined, e.g. a negative difference or a division by zero.
+    Undefined,
+    /// The result does not fit in `Value`.
+    Overflow,
 }
 
+/// Some operator sequence overflowed `Value`, so the equation could not be decided.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
-enum Operator {
-    Add,
-    Multiply,
-    Concatenate,
+struct Overflowed;
+
+/// What undoing an operator tells a search about the operand it is missing.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Inverse {
+    /// Exactly this operand works.
+    Exact(Value),
+    /// Every operand in one of these sorted, disjoint inclusive ranges works, e.g.
+    /// `left / 3 == 4` for `left` in `12..=14`.
+    Ranges(Vec<(Value, Value)>),
+    /// Every operand works, e.g. `left * 0 == 0`.
+    Any,
+    /// No operand works, so the branch is pruned.
+    Impossible,
+    /// Undoing needs a number past `Value`, as applying the operator would, so the branch is undecided.
+    Overflow,
+    /// The operator cannot be undone to a single value; search forwards instead.
+    Unsupported,
 }
 
-fn part1(content: &str) -> u128 {
+/// A binary operator placed between calibration values, evaluated left to right.
+trait Operator: Sync {
+    /// How the operator is written, e.g. `+` or `||`.
+    fn symbol(&self) -> &'static str;
+    /// How tightly the operator binds when evaluating with precedence; higher binds first.
+    fn precedence(&self) -> u8;
+    /// Whether `left op right` is never smaller than either operand when both are at least 1,
+    /// so a partial result above the target can never come back down.
+    fn grows(&self) -> bool {
+        false
+    }
+    /// `left op right`, or why it has no value.
+    fn apply(&self, left: &Value, right: &Value) -> Result<Value, Fault>;
+    /// Solves `left op right == target` for `left`, letting the solver prune.
+    fn inverse(&self, _target: &Value, _right: &Value) -> Inverse {
+        Inverse::Unsupported
+    }
+    /// Solves `left op right == target` for `right`, used when grouping with parentheses.
+    fn inverse_right(&self, _target: &Value, _left: &Value) -> Inverse {
+        Inverse::Unsupported
+    }
+    /// Like `inverse`, for every target in `lo..=hi` at once. The left-to-right search
+    /// needs this once an operator such as `/` has turned its target into a range.
+    fn inverse_range(&self, _lo: &Value, _hi: &Value, _right: &Value) -> Inverse {
+        Inverse::Unsupported
+    }
+}
 
-    let entries = match parse_input(&content) {
-        Ok(entries) => entries, // If successful, get the Vec<Entry>
-        Err(_) => return 0, // Handle the error case (return 0 or some default value)
-    };
+/// `numerator / denominator` rounded up; `denominator` must not be zero.
+fn div_ceil(numerator: &Value, denominator: &Value) -> Value {
+    let (quotient, remainder) = value::div_rem(numerator, denominator);
+    if value::is_zero(&remainder) { quotient } else { quotient + Value::from(1u8) }
+}
+
+struct Add;
+struct Multiply;
+struct Concatenate;
+struct Subtract;
+struct IntegerDivide;
+struct Xor;
+
+impl Operator for Add {
+    fn symbol(&self) -> &'static str { "+" }
+    fn precedence(&self) -> u8 { 1 }
+    fn grows(&self) -> bool { true }
+    fn apply(&self, left: &Value, right: &Value) -> Result<Value, Fault> {
+        value::checked_add(left, right).ok_or(Fault::Overflow)
+    }
+    fn inverse(&self, target: &Value, right: &Value) -> Inverse {
+        if target >= right { Inverse::Exact(target - right) } else { Inverse::Impossible }
+    }
+    fn inverse_right(&self, target: &Value, left: &Value) -> Inverse {
+        self.inverse(target, left)
+    }
+    fn inverse_range(&self, lo: &Value, hi: &Value, right: &Value) -> Inverse {
+        if hi < right {
+            return Inverse::Impossible;
+        }
+        let first = if lo > right { lo - right } else { Value::from(0u8) };
+        Inverse::Ranges(vec![(first, hi - right)])
+    }
+}
+
+impl Operator for Multiply {
+    fn symbol(&self) -> &'static str { "*" }
+    fn precedence(&self) -> u8 { 2 }
+    fn grows(&self) -> bool { true }
+    fn apply(&self, left: &Value, right: &Value) -> Result<Value, Fault> {
+        value::checked_mul(left, right).ok_or(Fault::Overflow)
+    }
+    fn inverse(&self, target: &Value, right: &Value) -> Inverse {
+        if value::is_zero(right) {
+            if value::is_zero(target) { Inverse::Any } else { Inverse::Impossible }
+        } else if value::is_zero(&(target % right)) {
+            Inverse::Exact(target / right)
+        } else {
+            Inverse::Impossible
+        }
+    }
+    fn inverse_right(&self, target: &Value, left: &Value) -> Inverse {
+        self.inverse(target, left)
+    }
+    fn inverse_range(&self, lo: &Value, hi: &Value, right: &Value) -> Inverse {
+        if value::is_zero(right) {
+            return if value::is_zero(lo) { Inverse::Any } else { Inverse::Impossible };
+        }
+        let (first, last) = (div_ceil(lo, right), hi / right);
+        if first <= last { Inverse::Ranges(vec![(first, last)]) } else { Inverse::Impossible }
+    }
+}
+
+impl Operator for Concatenate {
+    fn symbol(&self) -> &'static str { "||" }
+    fn precedence(&self) -> u8 { 3 }
+    fn grows(&self) -> bool { true }
+    fn apply(&self, left: &Value, right: &Value) -> Result<Value, Fault> {
+        // `left || right` = left * 10^digits(right) + right
+        let shift = value::pow10(value::digits(right)).ok_or(Fault::Overflow)?;
+        let shifted = value::checked_mul(left, &shift).ok_or(Fault::Overflow)?;
+        value::checked_add(&shifted, right).ok_or(Fault::Overflow)
+    }
+    fn inverse(&self, target: &Value, right: &Value) -> Inverse {
+        // Concatenating never shrinks `right`, so a smaller target is ruled out before shifting.
+        if target < right {
+            return Inverse::Impossible;
+        }
+        let Some(shift) = value::pow10(value::digits(right)) else { return Inverse::Overflow };
+        match value::div_rem(target, &shift) {
+            (left, suffix) if suffix == *right => Inverse::Exact(left),
+            _ => Inverse::Impossible,
+        }
+    }
+    fn inverse_right(&self, target: &Value, left: &Value) -> Inverse {
+        // `right` is the last `d` digits of the target, where `d` is its own digit count.
+        for d in 1..=value::digits(target) {
+            let Some(shift) = value::pow10(d) else { return Inverse::Overflow };
+            let (head, right) = value::div_rem(target, &shift);
+            if head == *left && value::digits(&right) == d {
+                return Inverse::Exact(right);
+            }
+        }
+        Inverse::Impossible
+    }
+    fn inverse_range(&self, lo: &Value, hi: &Value, right: &Value) -> Inverse {
+        // `left * shift` has to land in the range shifted down by `right`.
+        if hi < right {
+            return Inverse::Impossible;
+        }
+        let Some(shift) = value::pow10(value::digits(right)) else { return Inverse::Overflow };
+        let first = if lo > right { div_ceil(&(lo - right), &shift) } else { Value::from(0u8) };
+        let (last, _) = value::div_rem(&(hi - right), &shift);
+        if first <= last { Inverse::Ranges(vec![(first, last)]) } else { Inverse::Impossible }
+    }
+}
+
+impl Operator for Subtract {
+    fn symbol(&self) -> &'static str { "-" }
+    fn precedence(&self) -> u8 { 1 }
+    fn apply(&self, left: &Value, right: &Value) -> Result<Value, Fault> {
+        if left >= right { Ok(left - right) } else { Err(Fault::Undefined) }
+    }
+    // A left operand past `Value`'s range could not have been computed, so it is no solution.
+    fn inverse(&self, target: &Value, right: &Value) -> Inverse {
+        value::checked_add(target, right).map_or(Inverse::Impossible, Inverse::Exact)
+    }
+    fn inverse_right(&self, target: &Value, left: &Value) -> Inverse {
+        if left >= target { Inverse::Exact(left - target) } else { Inverse::Impossible }
+    }
+    fn inverse_range(&self, lo: &Value, hi: &Value, right: &Value) -> Inverse {
+        match value::checked_add(lo, right) {
+            Some(first) => Inverse::Ranges(vec![(first, value::saturating_add(hi, right))]),
+            None => Inverse::Impossible,
+        }
+    }
+}
+
+impl Operator for IntegerDivide {
+    fn symbol(&self) -> &'static str { "/" }
+    fn precedence(&self) -> u8 { 2 }
+    fn apply(&self, left: &Value, right: &Value) -> Result<Value, Fault> {
+        if value::is_zero(right) { Err(Fault::Undefined) } else { Ok(left / right) }
+    }
+    // Rounding down maps a whole range of left operands to one result.
+    fn inverse(&self, target: &Value, right: &Value) -> Inverse {
+        self.inverse_range(target, target, right)
+    }
+    fn inverse_range(&self, lo: &Value, hi: &Value, right: &Value) -> Inverse {
+        if value::is_zero(right) {
+            return Inverse::Impossible;
+        }
+        // `left / right` is in `lo..=hi` exactly for `left` in `lo * right..=hi * right + right - 1`,
+        // cut off where `Value` ends.
+        match value::checked_mul(lo, right) {
+            Some(first) => {
+                let last = value::saturating_add(&value::saturating_mul(hi, right), &(right - Value::from(1u8)));
+                Inverse::Ranges(vec![(first, last)])
+            }
+            None => Inverse::Impossible,
+        }
+    }
+}
+
+impl Operator for Xor {
+    fn symbol(&self) -> &'static str { "^" }
+    fn precedence(&self) -> u8 { 0 }
+    fn apply(&self, left: &Value, right: &Value) -> Result<Value, Fault> {
+        Ok(left ^ right)
+    }
+    fn inverse(&self, target: &Value, right: &Value) -> Inverse {
+        Inverse::Exact(value::xor(target, right))
+    }
+    fn inverse_right(&self, target: &Value, left: &Value) -> Inverse {
+        self.inverse(target, left)
+    }
+    fn inverse_range(&self, lo: &Value, hi: &Value, right: &Value) -> Inverse {
+        // Cut the range into aligned blocks of 2^k values; xor maps each block onto another one.
+        let (one, two) = (Value::from(1u8), Value::from(2u8));
+        let mut blocks: Vec<(Value, Value)> = Vec::new();
+        let mut start = lo.to_owned();
+        loop {
+            // The largest aligned block that starts at `start` and ends by `hi`.
+            let mut size = one.to_owned();
+            while let Some(next) = value::checked_mul(&size, &two) {
+                let (first, last) = value::block(&start, &next);
+                if first != start || last > *hi {
+                    break;
+                }
+                size = next;
+            }
+            blocks.push(value::block(&value::xor(&start, right), &size));
+            let (_, last) = value::block(&start, &size);
+            match value::checked_add(&last, &one) {
+                Some(next) if next <= *hi => start = next,
+                _ => break,
+            }
+        }
+        blocks.sort();
+        let mut ranges: Vec<(Value, Value)> = Vec::with_capacity(blocks.len());
+        for (first, last) in blocks {
+            match ranges.last_mut() {
+                Some(previous) if value::checked_add(&previous.1, &one).is_none_or(|after| first <= after) => {
+                    if last > previous.1 {
+                        previous.1 = last;
+                    }
+                }
+                _ => ranges.push((first, last)),
+            }
+        }
+        Inverse::Ranges(ranges)
+    }
+}
+
+/// Looks up a built-in operator by symbol or name.
+fn operator_by_name(name: &str) -> Option<&'static dyn Operator> {
+    match name {
+        "+" | "add" => Some(&Add),
+        "*" | "mul" => Some(&Multiply),
+        "||" | "concat" => Some(&Concatenate),
+        "-" | "sub" => Some(&Subtract),
+        "/" | "div" => Some(&IntegerDivide),
+        "^" | "xor" => Some(&Xor),
+        _ => None,
+    }
+}
+
+/// Parses a comma-separated operator set such as `+,*,||`.
+fn parse_operators(list: &str) -> Result<Vec<&'static dyn Operator>, String> {
+    list.split(',')
+        .map(|name| operator_by_name(name.trim()).ok_or(format!("unknown operator '{}'", name.trim())))
+        .collect()
+}
+
+/// How the values and operators of an equation are grouped when it is evaluated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Evaluation {
+    /// Strictly left to right, as the puzzle specifies.
+    LeftToRight,
+    /// Tighter-binding operators first (see `Operator::precedence`), then left to right.
+    Precedence,
+    /// Any parenthesization of the values.
+    Parentheses,
+}
+
+impl Evaluation {
+    fn parse(name: &str) -> Option<Self> {
+        match name {
+            "left" | "ltr" => Some(Evaluation::LeftToRight),
+            "precedence" | "prec" => Some(Evaluation::Precedence),
+            "parens" | "parentheses" => Some(Evaluation::Parentheses),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Evaluation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Evaluation::LeftToRight => write!(f, "left to right"),
+            Evaluation::Precedence => write!(f, "with precedence"),
+            Evaluation::Parentheses => write!(f, "with any parentheses"),
+        }
+    }
+}
+
+const PART1_OPERATORS: [&'static dyn Operator; 2] = [&Add, &Multiply];
+const PART2_OPERATORS: [&'static dyn Operator; 3] = [&Add, &Multiply, &Concatenate];
+
+fn part1(content: &str) -> Result<Value, CalibrationError> {
+    let entries = parse_input(content)?;
     // Calculate the sum of matching prefixes
-    let matching_sum = sum_matching_prefixes(&entries);
-    matching_sum
+    sum_matching_prefixes(&entries, &PART1_OPERATORS, Evaluation::LeftToRight)
 }
 
-fn parse_input(input: &str) -> Result<Vec<Entry>, Box<dyn Error>> {
+fn parse_input(input: &str) -> Result<Vec<Entry>, CalibrationError> {
     input
         .lines()
-        .filter(|line| !line.trim().is_empty())
-        .map(|line| parse_line(line))
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(index, line)| parse_line(index + 1, line))
         .collect()
 }
 
-fn parse_line(line: &str) -> Result<Entry, Box<dyn Error>> {
-    let parts: Vec<&str> = line.split(':').collect();
+fn parse_line(line: usize, text: &str) -> Result<Entry, CalibrationError> {
+    let parts: Vec<&str> = text.split(':').collect();
     if parts.len() != 2 {
-        return Err(format!("Line '{}' is not properly formatted.", line).into());
+        return Err(CalibrationError::Malformed { line });
     }
-    let prefix: u128 = parts[0].trim().parse()?;
-    let values: Vec<u128> = parts[1]
-        .trim()
+    let prefix = parse_number(line, parts[0].trim())?;
+    let values: Vec<Value> = parts[1]
         .split_whitespace()
-        .map(|num_str| num_str.parse())
-        .collect::<Result<Vec<u128>, _>>()?;
-    Ok(Entry { prefix, values })
+        .map(|num_str| parse_number(line, num_str))
+        .collect::<Result<Vec<Value>, _>>()?;
+    Ok(Entry { line, prefix, values })
 }
 
+/// Parses one number, telling a value too large for `Value` apart from one that is not a number.
+fn parse_number(line: usize, text: &str) -> Result<Value, CalibrationError> {
+    value::parse(text).ok_or_else(|| {
+        if !text.is_empty() && text.bytes().all(|b| b.is_ascii_digit()) {
+            CalibrationError::TooLarge { line, text: text.to_string() }
+        } else {
+            CalibrationError::InvalidNumber { line, text: text.to_string() }
+        }
+    })
+}
 
-/// Generates all possible operator sequences (`+` or `*`) for `n` operator positions.
-fn generate_operator_sequences(n: usize) -> Vec<Vec<char>> {
-    let total_combinations = 1 << n; // 2^n combinations
-    let mut sequences = Vec::new();
+/// Checks if some choice of `operators`, evaluated under `evaluation`, turns `values` into `prefix`.
+fn check_operator_sequences(
+    prefix: &Value,
+    values: &[Value],
+    operators: &[&'static dyn Operator],
+    evaluation: Evaluation,
+) -> Result<bool, Overflowed> {
+    match evaluation {
+        Evaluation::Parentheses => Groupings::new(values, operators, prefix).reaches(),
+        _ => search(evaluation, prefix, values, operators, &mut |_| true),
+    }
+}
 
-    for mask in 0..total_combinations {
-        let mut seq = Vec::with_capacity(n);
-        for i in 0..n {
-            // If the i-th bit is set, use '+', else use '*'
-            if (mask & (1 << i)) != 0 {
-                seq.push('+');
-            } else {
-                seq.push('*');
-            }
-        }
-        sequences.push(seq);
+/// The first solving expression found, rendered like `81 + 40 * 27 = 3267`.
+fn find_witness(
+    prefix: &Value,
+    values: &[Value],
+    operators: &[&'static dyn Operator],
+    evaluation: Evaluation,
+) -> Result<Option<String>, Overflowed> {
+    if evaluation == Evaluation::Parentheses {
+        return Groupings::new(values, operators, prefix).witness();
     }
+    let mut witness = None;
+    search(evaluation, prefix, values, operators, &mut |sequence| {
+        witness = Some(render_expression(prefix, values, sequence));
+        true
+    })?;
+    Ok(witness)
+}
 
-    sequences
+/// Number of distinct ways to solve the equation: operator sequences, or with
+/// parentheses, operator sequences times groupings (so `(a + b) + c` and
+/// `a + (b + c)` count separately).
+fn count_solutions(
+    prefix: &Value,
+    values: &[Value],
+    operators: &[&'static dyn Operator],
+    evaluation: Evaluation,
+) -> Result<u128, Overflowed> {
+    if evaluation == Evaluation::Parentheses {
+        return Groupings::new(values, operators, prefix).count();
+    }
+    let mut count = 0;
+    search(evaluation, prefix, values, operators, &mut |_| {
+        count += 1;
+        false
+    })?;
+    Ok(count)
 }
 
-/// Evaluates the expression based on the operator sequence, left-to-right.
-fn evaluate_expression(values: &[u128], operators: &[char]) -> u128 {
-    let mut result: u128 = values[0];
-    for (i, &op) in operators.iter().enumerate() {
-        match op {
-            '+' => result += values[i + 1],
-            '*' => result *= values[i + 1],
-            _ => panic!("Unsupported operator: {}", op),
-        }
+/// Runs the operator-sequence search for a mode that needs no parentheses.
+fn search(
+    evaluation: Evaluation,
+    prefix: &Value,
+    values: &[Value],
+    operators: &[&'static dyn Operator],
+    found: &mut dyn FnMut(&[&'static dyn Operator]) -> bool,
+) -> Result<bool, Overflowed> {
+    match evaluation {
+        Evaluation::LeftToRight => find_sequences(prefix, values, operators, found),
+        Evaluation::Precedence => find_precedence_sequences(prefix, values, operators, found),
+        Evaluation::Parentheses => unreachable!("parentheses are searched by `Groupings`"),
+    }
+}
+
+/// Whether partial results above `limit` can be dropped: every operator grows its
+/// operands and no value is 0, so nothing ever shrinks back down.
+fn can_prune(values: &[Value], operators: &[&'static dyn Operator]) -> bool {
+    operators.iter().all(|op| op.grows()) && !values.iter().any(value::is_zero)
+}
+
+/// Renders an equation such as `81 + 40 * 27 = 3267`.
+fn render_expression(prefix: &Value, values: &[Value], sequence: &[&'static dyn Operator]) -> String {
+    let mut text = values[0].to_string();
+    for (op, value) in sequence.iter().zip(&values[1..]) {
+        text.push_str(&format!(" {} {}", op.symbol(), value));
     }
-    result
+    format!("{} = {}", text, prefix)
 }
 
-/// Checks if any operator sequence matches the prefix.
-fn check_operator_sequences(prefix: u128, values: &[u128]) -> bool {
-    if values.len() == 0 {
-        return false;
+/// The prefix table of `find_sequences` lists at most this many operator sequences.
+const PREFIX_TABLE: usize = 1 << 16;
+
+/// Calls `found` with each operator sequence (left to right) under which `values`
+/// evaluate to `prefix`, until `found` returns true. Returns whether it did, or
+/// `Overflowed` if it did not and some branch could not be evaluated in `Value`.
+///
+/// Works from the last value backwards: the final operator must produce `prefix`,
+/// so each operator is undone on the target and the remaining values must produce
+/// the result. Operators that cannot be undone (a negative difference, a product
+/// that does not divide, a missing decimal suffix) cut the branch off immediately.
+/// `/` turns the target into a range, and everything undone after it works on that
+/// range; once a range reaches the first half of the values it is looked up in a
+/// sorted table of everything that half produces, built the first time a range
+/// gets there, so the search meets in the middle. Operators without an inverse
+/// fall back to trying every value the remaining values can produce.
+fn find_sequences(
+    prefix: &Value,
+    values: &[Value],
+    operators: &[&'static dyn Operator],
+    found: &mut dyn FnMut(&[&'static dyn Operator]) -> bool,
+) -> Result<bool, Overflowed> {
+    struct Backward<'a> {
+        values: &'a [Value],
+        operators: &'a [&'static dyn Operator],
+        prune: bool,
+        /// Number of leading values whose ranges of targets are answered from `table`.
+        split: usize,
+        /// Every value of `values[..split]` with its operator sequence (see `list`), sorted.
+        table: Option<Vec<(Value, usize)>>,
+        /// The trailing operators chosen so far, last-first.
+        suffix: Vec<&'static dyn Operator>,
+        overflowed: Cell<bool>,
+        found: &'a mut dyn FnMut(&[&'static dyn Operator]) -> bool,
     }
-    if values.len() == 1 {
-        return values[0] == prefix;
+
+    impl Backward<'_> {
+        /// Looks for sequences under which `values[..len]` make something in `lo..=hi`.
+        fn walk(&mut self, lo: &Value, hi: &Value, len: usize) -> bool {
+            if len == 1 {
+                let first = &self.values[0];
+                return *lo <= *first && *first <= *hi && report(&[], &self.suffix, self.found);
+            }
+            // Single targets are only worth a lookup once a range has built the table; a
+            // single target that went past the split before that keeps walking to the start.
+            if len == self.split && (lo != hi || self.table.is_some()) {
+                return self.lookup(lo, hi);
+            }
+            let (rest, last) = (&self.values[..len - 1], &self.values[len - 1]);
+            for &op in self.operators {
+                self.suffix.push(op);
+                let inverse = if lo == hi { op.inverse(lo, last) } else { op.inverse_range(lo, hi, last) };
+                let stop = match inverse {
+                    Inverse::Exact(left) => self.walk(&left, &left, len - 1),
+                    Inverse::Ranges(ranges) => ranges.iter().any(|(first, end)| self.walk(first, end, len - 1)),
+                    Inverse::Any => {
+                        let (suffix, found) = (&self.suffix, &mut *self.found);
+                        forward(rest, self.operators, &self.overflowed, &mut |head, _| report(head, suffix, found))
+                    }
+                    Inverse::Impossible => false,
+                    // When pruning, an overflow is just another value above the target.
+                    Inverse::Overflow => {
+                        if !self.prune {
+                            self.overflowed.set(true);
+                        }
+                        false
+                    }
+                    Inverse::Unsupported => {
+                        let (suffix, found, overflowed) = (&self.suffix, &mut *self.found, &self.overflowed);
+                        forward(rest, self.operators, overflowed, &mut |head, left| match op.apply(left, last) {
+                            Ok(result) => *lo <= result && result <= *hi && report(head, suffix, found),
+                            Err(fault) => {
+                                if fault == Fault::Overflow {
+                                    overflowed.set(true);
+                                }
+                                false
+                            }
+                        })
+                    }
+                };
+                self.suffix.pop();
+                if stop {
+                    return true;
+                }
+            }
+            false
+        }
+
+        fn lookup(&mut self, lo: &Value, hi: &Value) -> bool {
+            if self.table.is_none() {
+                let mut table = Vec::new();
+                let overflowed = list(&self.values[0], &self.values[1..self.split], self.operators, 0, &mut table);
+                // Without pruning an overflowed prefix might have come back down to the target.
+                if overflowed && !self.prune {
+                    self.overflowed.set(true);
+                }
+                table.sort();
+                self.table = Some(table);
+            }
+            let table = self.table.as_deref().unwrap_or_default();
+            let start = table.partition_point(|(value, _)| value < lo);
+            table[start..].iter().take_while(|(value, _)| value <= hi).any(|&(_, code)| {
+                let head = decode(code, self.split - 1, self.operators);
+                report(&head, &self.suffix, self.found)
+            })
+        }
     }
 
-    let n = values.len() - 1;
-    let operator_sequences = generate_operator_sequences(n);
+    /// Pushes the value of `acc` followed by `rest` under every operator sequence,
+    /// with the sequence coded as digits in base `operators.len()`, first operator
+    /// most significant. Returns whether some sequence overflowed.
+    fn list(
+        acc: &Value,
+        rest: &[Value],
+        operators: &[&'static dyn Operator],
+        code: usize,
+        table: &mut Vec<(Value, usize)>,
+    ) -> bool {
+        let [next, tail @ ..] = rest else {
+            table.push((acc.to_owned(), code));
+            return false;
+        };
+        let mut overflowed = false;
+        for (index, op) in operators.iter().enumerate() {
+            match op.apply(acc, next) {
+                Ok(value) => overflowed |= list(&value, tail, operators, code * operators.len() + index, table),
+                Err(fault) => overflowed |= fault == Fault::Overflow,
+            }
+        }
+        overflowed
+    }
 
-    for ops in operator_sequences {
-        let result = evaluate_expression(values, &ops);
-        if result == prefix {
-            return true;
+    /// The `len` operators coded by `list`.
+    fn decode(mut code: usize, len: usize, operators: &[&'static dyn Operator]) -> Vec<&'static dyn Operator> {
+        let mut head = vec![operators[0]; len];
+        for slot in head.iter_mut().rev() {
+            *slot = operators[code % operators.len()];
+            code /= operators.len();
         }
+        head
     }
 
-    false
+    /// `suffix` holds the trailing operators last-first.
+    fn report(
+        head: &[&'static dyn Operator],
+        suffix: &[&'static dyn Operator],
+        found: &mut dyn FnMut(&[&'static dyn Operator]) -> bool,
+    ) -> bool {
+        let sequence: Vec<&'static dyn Operator> = head.iter().chain(suffix.iter().rev()).copied().collect();
+        found(&sequence)
+    }
+
+    if values.is_empty() {
+        return Ok(false);
+    }
+    // Split halfway, keeping the table to at most `PREFIX_TABLE` sequences.
+    let mut split = values.len().div_ceil(2);
+    while split > 1 && operators.len().checked_pow(split as u32 - 1).is_none_or(|size| size > PREFIX_TABLE) {
+        split -= 1;
+    }
+    let mut search = Backward {
+        values,
+        operators,
+        prune: can_prune(values, operators),
+        split,
+        table: None,
+        suffix: Vec::new(),
+        overflowed: Cell::new(false),
+        found,
+    };
+    if search.walk(prefix, prefix, values.len()) {
+        Ok(true)
+    } else if search.overflowed.get() {
+        Err(Overflowed)
+    } else {
+        Ok(false)
+    }
 }
 
-/// Function to sum the prefixes where operator sequences match.
-fn sum_matching_prefixes(entries: &[Entry]) -> u128 {
-    let mut total_sum = 0;
-    for entry in entries {
-        if check_operator_sequences(entry.prefix, &entry.values) {
-            total_sum += entry.prefix;
+/// Evaluates `values` left to right under every operator choice, passing each
+/// sequence and its result to `visit` until it returns true. Sequences that
+/// overflow are skipped and flagged in `overflowed`.
+fn forward(
+    values: &[Value],
+    operators: &[&'static dyn Operator],
+    overflowed: &Cell<bool>,
+    visit: &mut dyn FnMut(&[&'static dyn Operator], &Value) -> bool,
+) -> bool {
+    fn walk(
+        acc: &Value,
+        rest: &[Value],
+        operators: &[&'static dyn Operator],
+        head: &mut Vec<&'static dyn Operator>,
+        overflowed: &Cell<bool>,
+        visit: &mut dyn FnMut(&[&'static dyn Operator], &Value) -> bool,
+    ) -> bool {
+        let [next, tail @ ..] = rest else { return visit(head, acc) };
+        for &op in operators {
+            match op.apply(acc, next) {
+                Ok(value) => {
+                    head.push(op);
+                    let stop = walk(&value, tail, operators, head, overflowed, visit);
+                    head.pop();
+                    if stop {
+                        return true;
+                    }
+                }
+                Err(Fault::Overflow) => overflowed.set(true),
+                Err(Fault::Undefined) => {}
+            }
         }
+        false
+    }
+    match values {
+        [] => false,
+        [first, rest @ ..] => walk(first, rest, operators, &mut Vec::new(), overflowed, visit),
     }
-    total_sum
 }
 
-fn part2(content: &str) -> u128 {
-    let entries = match parse_input(content) {
-        Ok(entries) => entries, // If successful, get the Vec<Entry>
-        Err(_) => return 0, // Handle the error case (return 0 or some default value)
-    };
-    let part2_result = sum_matching_prefixes_for_part2(&entries);
-    part2_result
-}
-
-/// 条件を満たすエントリのプレフィックスを合計する関数
-fn sum_matching_prefixes_for_part2(entries: &[Entry]) -> u128 {
-    entries.iter()
-        .filter(|entry| check_operator_sequences_part2(entry.prefix, &entry.values))
-        .map(|entry| entry.prefix)
-        .sum()
-}
-
-fn generate_operator_sequences_part2(n: usize) -> Vec<Vec<Operator>> {
-    let total_combinations = 3usize.pow(n as u32); // 3^n combinations
-    let mut sequences = Vec::with_capacity(total_combinations);
-    
-    for mask in 0..total_combinations {
-        let mut seq = Vec::with_capacity(n);
-        let mut temp = mask;
-        for _ in 0..n {
-            let op_index = temp % 3;
-            let op = match op_index {
-                0 => Operator::Add,
-                1 => Operator::Multiply,
-                2 => Operator::Concatenate,
-                _ => unreachable!(),
+/// Like `find_sequences`, but each sequence is evaluated with precedence: tighter
+/// operators are applied first and equal ones left to right.
+///
+/// Runs forwards as in shunting-yard, keeping operands that still wait for a looser
+/// operator on a stack. Choosing the next operator first reduces every waiting
+/// operator that binds at least as tightly.
+fn find_precedence_sequences(
+    prefix: &Value,
+    values: &[Value],
+    operators: &[&'static dyn Operator],
+    found: &mut dyn FnMut(&[&'static dyn Operator]) -> bool,
+) -> Result<bool, Overflowed> {
+    struct Search<'a> {
+        prefix: &'a Value,
+        operators: &'a [&'static dyn Operator],
+        prune: bool,
+        overflowed: bool,
+        head: Vec<&'static dyn Operator>,
+        found: &'a mut dyn FnMut(&[&'static dyn Operator]) -> bool,
+    }
+
+    impl Search<'_> {
+        /// `operands` has one more entry than `pending`; its last entry is the newest value.
+        fn walk(&mut self, operands: Vec<Value>, pending: Vec<&'static dyn Operator>, rest: &[Value]) -> bool {
+            let Some((next, tail)) = rest.split_first() else {
+                let (mut operands, mut pending) = (operands, pending);
+                return match reduce(&mut operands, &mut pending, 0) {
+                    Ok(()) => operands[0] == *self.prefix && (self.found)(&self.head),
+                    Err(fault) => self.fail(fault),
+                };
             };
-            seq.push(op);
-            temp /= 3;
+            for &op in self.operators {
+                let (mut operands, mut pending) = (operands.clone(), pending.clone());
+                if let Err(fault) = reduce(&mut operands, &mut pending, op.precedence()) {
+                    self.fail(fault);
+                    continue;
+                }
+                if self.prune && operands.last().is_some_and(|value| value > self.prefix) {
+                    continue;
+                }
+                operands.push(next.to_owned());
+                pending.push(op);
+                self.head.push(op);
+                let stop = self.walk(operands, pending, tail);
+                self.head.pop();
+                if stop {
+                    return true;
+                }
+            }
+            false
+        }
+
+        /// When pruning, an overflow is just another value above the target.
+        fn fail(&mut self, fault: Fault) -> bool {
+            self.overflowed |= fault == Fault::Overflow && !self.prune;
+            false
+        }
+    }
+
+    /// Applies the waiting operators that bind at least as tightly as `min`, innermost first.
+    fn reduce(operands: &mut Vec<Value>, pending: &mut Vec<&'static dyn Operator>, min: u8) -> Result<(), Fault> {
+        while let Some(&op) = pending.last() {
+            if op.precedence() < min {
+                break;
+            }
+            let right = operands.pop().expect("one operand per operator");
+            let left = operands.pop().expect("one operand per operator");
+            operands.push(op.apply(&left, &right)?);
+            pending.pop();
         }
-        sequences.push(seq);
+        Ok(())
+    }
+
+    let Some((first, rest)) = values.split_first() else { return Ok(false) };
+    let mut search = Search {
+        prefix,
+        operators,
+        prune: can_prune(values, operators),
+        overflowed: false,
+        head: Vec::new(),
+        found,
+    };
+    if search.walk(vec![first.to_owned()], Vec::new(), rest) {
+        Ok(true)
+    } else if search.overflowed {
+        Err(Overflowed)
+    } else {
+        Ok(false)
     }
-    
-    sequences
 }
 
-fn evaluate_expression_part2(values: &[u128], operators: &[Operator]) -> Option<u128> {
-    if values.is_empty() {
-        return None;
-    }
-    
-    let mut result = values[0];
-    
-    for (i, &op) in operators.iter().enumerate() {
-        if i + 1 >= values.len() {
-            return None; // 演算子が値より多い場合は無効
-        }
-        let next = values[i + 1];
-        match op {
-            Operator::Add => {
-                result += next;
-            }
-            Operator::Multiply => {
-                result *= next;
-            }
-            Operator::Concatenate => {
-                // Concatenation: result と next を結合
-                let concatenated = result.to_string() + &next.to_string();
-                match concatenated.parse::<u128>() {
-                    Ok(val) => result = val,
-                    Err(_) => return None, // パースエラー（オーバーフローなど）
+/// Runs up to this many values are answered from their full value set; longer
+/// ones are queried per target, which would otherwise multiply the queries.
+const LISTED_RUN: usize = 6;
+
+/// Counts the ways to make a target from a run of values under some operator
+/// choice and some parenthesization. The last operator applied splits the run in
+/// two; every value of the shorter side is listed (bottom-up, then cached) and the
+/// operator is undone to find the target the longer side must make, which is
+/// queried the same way. Runs of at most `LISTED_RUN` values are looked up in
+/// their own sets instead. When the operator cannot be undone to one value (`/`
+/// gives a range of left operands and no right one, `* 0` allows anything), the
+/// longer side is listed in full as well and filtered, so with those operators the
+/// work grows with the number of values the whole run makes, exponentially in its
+/// length.
+struct Groupings<'a> {
+    values: &'a [Value],
+    operators: &'a [&'static dyn Operator],
+    target: &'a Value,
+    prune: bool,
+    /// Every value of `values[i..=j]` with its number of ways, listed on demand.
+    sets: HashMap<(usize, usize), HashMap<Value, u128>>,
+    /// Ways for `values[i..=j]` to make a value, memoized per query.
+    queries: HashMap<(usize, usize, Value), u128>,
+    overflowed: bool,
+}
+
+/// The last operator applied in one way of making a run's target: `left op right`,
+/// where `left` comes from the run up to `values[k]` and `right` from the rest.
+struct Split {
+    k: usize,
+    op: &'static dyn Operator,
+    left: Value,
+    right: Value,
+    /// Ways to make `left` times ways to make `right`.
+    ways: u128,
+}
+
+impl<'a> Groupings<'a> {
+    /// Values above `target` are dropped when `can_prune` allows.
+    fn new(values: &'a [Value], operators: &'a [&'static dyn Operator], target: &'a Value) -> Self {
+        Groupings {
+            values,
+            operators,
+            target,
+            prune: can_prune(values, operators),
+            sets: HashMap::new(),
+            queries: HashMap::new(),
+            overflowed: false,
+        }
+    }
+
+    fn reaches(&mut self) -> Result<bool, Overflowed> {
+        match self.total() {
+            0 if self.overflowed => Err(Overflowed),
+            0 => Ok(false),
+            _ => Ok(true),
+        }
+    }
+
+    fn count(&mut self) -> Result<u128, Overflowed> {
+        let ways = self.total();
+        if self.overflowed { Err(Overflowed) } else { Ok(ways) }
+    }
+
+    fn witness(&mut self) -> Result<Option<String>, Overflowed> {
+        if !self.reaches()? {
+            return Ok(None);
+        }
+        let n = self.values.len();
+        Ok(Some(format!("{} = {}", self.render(0, n - 1, self.target, false), self.target)))
+    }
+
+    fn total(&mut self) -> u128 {
+        match self.values.len() {
+            0 => 0,
+            n => self.ways(0, n - 1, self.target),
+        }
+    }
+
+    /// Every value of `values[i..=j]`, built from the sets of its two sides at each split.
+    fn set(&mut self, i: usize, j: usize) -> &HashMap<Value, u128> {
+        if !self.sets.contains_key(&(i, j)) {
+            let mut here: HashMap<Value, u128> = HashMap::new();
+            if i == j {
+                here.insert(self.values[i].to_owned(), 1);
+            }
+            for k in i..j {
+                self.set(i, k);
+                self.set(k + 1, j);
+                let (lefts, rights) = (&self.sets[&(i, k)], &self.sets[&(k + 1, j)]);
+                for (left, left_ways) in lefts {
+                    for (right, right_ways) in rights {
+                        for op in self.operators {
+                            match op.apply(left, right) {
+                                Ok(result) if self.prune && result > *self.target => {}
+                                Ok(result) => {
+                                    let ways = here.entry(result).or_insert(0);
+                                    *ways = ways.saturating_add(left_ways.saturating_mul(*right_ways));
+                                }
+                                // When pruning, an overflow is just another value above the target.
+                                Err(fault) => self.overflowed |= fault == Fault::Overflow && !self.prune,
+                            }
+                        }
+                    }
                 }
             }
+            self.sets.insert((i, j), here);
         }
+        &self.sets[&(i, j)]
     }
-    
-    Some(result)
-}
 
+    /// Ways for `values[i..=j]` to make `target`.
+    fn ways(&mut self, i: usize, j: usize, target: &Value) -> u128 {
+        if j - i < LISTED_RUN {
+            return self.set(i, j).get(target).copied().unwrap_or(0);
+        }
+        let key = (i, j, target.to_owned());
+        if let Some(&ways) = self.queries.get(&key) {
+            return ways;
+        }
+        let mut ways = 0u128;
+        self.splits(i, j, target, &mut |split| {
+            ways = ways.saturating_add(split.ways);
+            false
+        });
+        self.queries.insert(key, ways);
+        ways
+    }
 
-fn check_operator_sequences_part2(prefix: u128, values: &[u128]) -> bool {
-    if values.len() == 0 {
-        return false;
+    /// Calls `visit` with each way to make `target` from `values[i..=j]` at the top
+    /// level, until it returns true.
+    fn splits(&mut self, i: usize, j: usize, target: &Value, visit: &mut dyn FnMut(Split) -> bool) -> bool {
+        for k in i..j {
+            // List the shorter side and undo each operator to find what the other must make.
+            let list_right = j - k <= k + 1 - i;
+            let listed: Vec<(Value, u128)> = if list_right {
+                self.set(k + 1, j).iter().map(|(value, &ways)| (value.to_owned(), ways)).collect()
+            } else {
+                self.set(i, k).iter().map(|(value, &ways)| (value.to_owned(), ways)).collect()
+            };
+            let (other_i, other_j) = if list_right { (i, k) } else { (k + 1, j) };
+            for (known, known_ways) in listed {
+                for &op in self.operators {
+                    let inverse = if list_right { op.inverse(target, &known) } else { op.inverse_right(target, &known) };
+                    let others: Vec<(Value, u128)> = match inverse {
+                        Inverse::Exact(other) => {
+                            let ways = self.ways(other_i, other_j, &other);
+                            if ways == 0 { Vec::new() } else { vec![(other, ways)] }
+                        }
+                        Inverse::Any => self.set(other_i, other_j).iter().map(|(value, &ways)| (value.to_owned(), ways)).collect(),
+                        Inverse::Ranges(ranges) => self
+                            .set(other_i, other_j)
+                            .iter()
+                            .filter(|(value, _)| ranges.iter().any(|(first, last)| first <= *value && *value <= last))
+                            .map(|(value, &ways)| (value.to_owned(), ways))
+                            .collect(),
+                        Inverse::Impossible => Vec::new(),
+                        Inverse::Overflow => {
+                            self.overflowed |= !self.prune;
+                            Vec::new()
+                        }
+                        Inverse::Unsupported => {
+                            let mut others = Vec::new();
+                            let (mut overflowed, prune) = (false, self.prune);
+                            for (other, &ways) in self.set(other_i, other_j) {
+                                let (left, right) = if list_right { (other, &known) } else { (&known, other) };
+                                match op.apply(left, right) {
+                                    Ok(result) if result == *target => others.push((other.to_owned(), ways)),
+                                    Ok(_) => {}
+                                    Err(fault) => overflowed |= fault == Fault::Overflow && !prune,
+                                }
+                            }
+                            self.overflowed |= overflowed;
+                            others
+                        }
+                    };
+                    for (other, other_ways) in others {
+                        let ways = known_ways.saturating_mul(other_ways);
+                        let (left, right) = if list_right { (other, known.to_owned()) } else { (known.to_owned(), other) };
+                        if visit(Split { k, op, left, right, ways }) {
+                            return true;
+                        }
+                    }
+                }
+            }
+        }
+        false
     }
-    if values.len() == 1 {
-        return values[0] == prefix;
+
+    /// Renders one way for `values[i..=j]` to make `target`, which must be reachable.
+    fn render(&mut self, i: usize, j: usize, target: &Value, nested: bool) -> String {
+        if i == j {
+            return self.values[i].to_string();
+        }
+        let mut found = None;
+        self.splits(i, j, target, &mut |split| {
+            found = Some(split);
+            true
+        });
+        let split = found.unwrap_or_else(|| unreachable!("{} is recorded as reachable", target));
+        let text = format!(
+            "{} {} {}",
+            self.render(i, split.k, &split.left, true),
+            split.op.symbol(),
+            self.render(split.k + 1, j, &split.right, true)
+        );
+        if nested { format!("({})", text) } else { text }
     }
-    
-    let n = values.len() - 1;
-    let operator_sequences = generate_operator_sequences_part2(n);
-    
-    for ops in operator_sequences {
-        if let Some(result) = evaluate_expression_part2(values, &ops) {
-            if result == prefix {
-                return true;
+}
+
+/// Prints a witness for every solvable equation (with `--count`, how many operator
+/// sequences solve it) followed by the equations no sequence solves and those
+/// that overflowed before they could be decided.
+fn audit(entries: &[Entry], operators: &[&'static dyn Operator], evaluation: Evaluation) {
+    let count = utils::has_flag("--count");
+    let mut unsolvable = Vec::new();
+    let mut overflowed = Vec::new();
+    for entry in entries {
+        match find_witness(&entry.prefix, &entry.values, operators, evaluation) {
+            Ok(Some(expression)) => {
+                if count {
+                    match count_solutions(&entry.prefix, &entry.values, operators, evaluation) {
+                        Ok(count) => println!("{}  ({} solution{})", expression, count, if count == 1 { "" } else { "s" }),
+                        Err(Overflowed) => println!("{}  (count overflowed {})", expression, value::NAME),
+                    }
+                } else {
+                    println!("{}", expression);
+                }
             }
+            Ok(None) => unsolvable.push(entry),
+            Err(Overflowed) => overflowed.push(entry),
+        }
+    }
+    println!("Unsolvable: {} of {}", unsolvable.len(), entries.len());
+    for entry in unsolvable {
+        println!("  {}", render_entry(entry));
+    }
+    if !overflowed.is_empty() {
+        println!("Overflowed {}: {} of {}", value::NAME, overflowed.len(), entries.len());
+        for entry in overflowed {
+            println!("  line {}: {}", entry.line, render_entry(entry));
         }
     }
-    
-    false
+}
+
+/// Renders an entry the way it appears in the input.
+fn render_entry(entry: &Entry) -> String {
+    let values: Vec<String> = entry.values.iter().map(Value::to_string).collect();
+    format!("{}: {}", entry.prefix, values.join(" "))
+}
+
+/// Function to sum the prefixes where operator sequences match.
+/// Fails naming every equation that overflowed, since the sum would be a guess without them.
+fn sum_matching_prefixes(
+    entries: &[Entry],
+    operators: &[&'static dyn Operator],
+    evaluation: Evaluation,
+) -> Result<Value, CalibrationError> {
+    let mut total = Value::from(0u8);
+    let mut overflowed = Vec::new();
+    for entry in entries {
+        match check_operator_sequences(&entry.prefix, &entry.values, operators, evaluation) {
+            Ok(true) => total = value::checked_add(&total, &entry.prefix).ok_or(CalibrationError::TotalOverflow)?,
+            Ok(false) => {}
+            Err(Overflowed) => overflowed.push(entry.line),
+        }
+    }
+    if overflowed.is_empty() {
+        Ok(total)
+    } else {
+        Err(CalibrationError::Overflow { lines: overflowed })
+    }
+}
+
+fn part2(content: &str) -> Result<Value, CalibrationError> {
+    let entries = parse_input(content)?;
+    sum_matching_prefixes(&entries, &PART2_OPERATORS, Evaluation::LeftToRight)
 }
 
 pub fn solve(day: u32) {
     println!("Started Day{}!",day );
 
     if let Ok(contents) = utils::read_file(&format!("src/solutions/day{}/input.txt", day)) {
-        let part1: u128 = part1(&contents) ; 
-        let part2 = part2(&contents);
-        println!("Part1:  {}", part1);
-        println!("Part2:  {}", part2);
+        match part1(&contents) {
+            Ok(sum) => println!("Part1:  {}", sum),
+            Err(e) => println!("Part1:  {}", e),
+        }
+        match part2(&contents) {
+            Ok(sum) => println!("Part2:  {}", sum),
+            Err(e) => println!("Part2:  {}", e),
+        }
+
+        let custom_operators = match utils::flag_value("--ops").map(|list| parse_operators(&list)).transpose() {
+            Ok(operators) => operators,
+            Err(e) => {
+                println!("{}", e);
+                return;
+            }
+        };
+        let evaluation = match utils::flag_value("--eval") {
+            None => Evaluation::LeftToRight,
+            Some(name) => match Evaluation::parse(&name) {
+                Some(evaluation) => evaluation,
+                None => {
+                    println!("invalid --eval '{}' (left, precedence or parens)", name);
+                    return;
+                }
+            },
+        };
+        let custom = custom_operators.is_some() || evaluation != Evaluation::LeftToRight;
+        let operators = custom_operators.unwrap_or_else(|| PART2_OPERATORS.to_vec());
+        if !custom && !utils::has_flag("--witness") {
+            return;
+        }
+        let entries = match parse_input(&contents) {
+            Ok(entries) => entries,
+            Err(e) => {
+                println!("{}", e);
+                return;
+            }
+        };
+        if custom {
+            let symbols: Vec<&str> = operators.iter().map(|op| op.symbol()).collect();
+            let label = format!("Operators [{}] {}", symbols.join(" "), evaluation);
+            match sum_matching_prefixes(&entries, &operators, evaluation) {
+                Ok(sum) => println!("{}:  {}", label, sum),
+                Err(e) => println!("{}:  {}", label, e),
+            }
+        }
+        if utils::has_flag("--witness") {
+            audit(&entries, &operators, evaluation);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A small xorshift generator, so the random equations are the same on every run.
+    struct Rng(u64);
+
+    impl Rng {
+        fn below(&mut self, n: u64) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0 % n
+        }
     }
-}
\ No newline at end of file
+
+    fn all_operators() -> Vec<&'static dyn Operator> {
+        ["+", "*", "||", "-", "/", "^"].iter().filter_map(|name| operator_by_name(name)).collect()
+    }
+
+    /// `values` evaluated left to right under `sequence`, or `None` if some step has no value.
+    fn evaluate(values: &[Value], sequence: &[&'static dyn Operator]) -> Option<Value> {
+        let mut acc = values[0].to_owned();
+        for (op, value) in sequence.iter().zip(&values[1..]) {
+            acc = op.apply(&acc, value).ok()?;
+        }
+        Some(acc)
+    }
+
+    /// Number of operator sequences under which `values` make `target`, trying every one.
+    fn brute_force(target: &Value, values: &[Value], operators: &[&'static dyn Operator]) -> u128 {
+        let slots = values.len() - 1;
+        let mut count = 0;
+        for mut code in 0..operators.len().pow(slots as u32) {
+            let sequence: Vec<&'static dyn Operator> = (0..slots)
+                .map(|_| {
+                    let op = operators[code % operators.len()];
+                    code /= operators.len();
+                    op
+                })
+                .collect();
+            if evaluate(values, &sequence).as_ref() == Some(target) {
+                count += 1;
+            }
+        }
+        count
+    }
+
+    #[test]
+    fn find_sequences_matches_brute_force() {
+        let all = all_operators();
+        let mut rng = Rng(0x2545_f491_4f6c_dd1d);
+        for _ in 0..3000 {
+            let operators: Vec<&'static dyn Operator> = all.iter().copied().filter(|_| rng.below(2) == 0).collect();
+            if operators.is_empty() {
+                continue;
+            }
+            let values: Vec<Value> = (0..1 + rng.below(6)).map(|_| Value::from(rng.below(20))).collect();
+            // Mostly targets some sequence reaches, so there is something to find.
+            let sequence: Vec<&'static dyn Operator> =
+                (1..values.len()).map(|_| operators[rng.below(operators.len() as u64) as usize]).collect();
+            let target = match evaluate(&values, &sequence) {
+                Some(target) if rng.below(4) != 0 => target,
+                _ => Value::from(rng.below(200)),
+            };
+
+            let mut count = 0;
+            let stopped = find_sequences(&target, &values, &operators, &mut |sequence| {
+                assert_eq!(evaluate(&values, sequence).as_ref(), Some(&target));
+                count += 1;
+                false
+            });
+            let symbols: Vec<&str> = operators.iter().map(|op| op.symbol()).collect();
+            assert_eq!(stopped, Ok(false));
+            assert_eq!(count, brute_force(&target, &values, &operators), "{}: {:?} with {:?}", target, values, symbols);
+        }
+    }
+
+    /// Undoing an operator past `u128::MAX` rules the branch out rather than overflowing.
+    #[cfg(not(feature = "bigint"))]
+    #[test]
+    fn inverses_past_u128_are_impossible() {
+        let operators = parse_operators("+,-,/").unwrap();
+        let solvable = |target: u128, values: &[u128]| {
+            find_sequences(&target, values, &operators, &mut |_| true)
+        };
+        assert_eq!(solvable(u128::MAX, &[5, 5]), Ok(false));
+        assert_eq!(solvable(1 << 127, &[3, 2]), Ok(false));
+        assert_eq!(solvable(u128::MAX, &[u128::MAX, 1]), Ok(true));
+        assert_eq!(solvable(u128::MAX - 2, &[u128::MAX, 2]), Ok(true));
+        assert_eq!(solvable(u128::MAX / 2, &[u128::MAX, 2, 1]), Ok(true));
+    }
+}
@@ -1,30 +1,111 @@
 use crate::utils;
 
-use std::collections::{HashMap, HashSet};
+use std::collections::BTreeMap;
+use std::fmt;
 
-fn compute_antinode_positions(p1: (usize, usize), p2: (usize, usize)) -> Vec<(usize, usize)> {
-    let (r1, c1) = p1;
-    let (r2, c2) = p2;
+/// A cell of a `D`-dimensional map: `[row, col]` for grids, `[layer, row, col]` for voxel maps.
+pub type Point<const D: usize> = [usize; D];
 
-    // 反節点1: p3 = 2 * p2 - p1
-    let p3_r = 2 * r2 as isize - r1 as isize;
-    let p3_c = 2 * c2 as isize - c1 as isize;
+/// Which cells on the line through two antennas `a` and `b` are antinodes.
+///
+/// A harmonic multiple `k` is the cell `k` antenna separations away from one antenna,
+/// measured through the other: `a + k(b - a)` and `b + k(a - b)`. Part 1 uses `k = 2`
+/// only; `k = 1` lands on the antennas themselves.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Harmonics {
+    /// Exactly these multiples.
+    Only(Vec<u64>),
+    /// Every multiple from `min` up to `max`, or up to the edge of the map.
+    Range { min: u64, max: Option<u64> },
+    /// Every cell on the line, including those between whole multiples when the
+    /// separation is not in lowest terms (part 2).
+    Line,
+}
+
+impl Harmonics {
+    /// Part 1: twice as far from one antenna as from the other.
+    pub fn part1() -> Self {
+        Harmonics::Only(vec![2])
+    }
 
-    // 反節点2: p4 = 2 * p1 - p2
-    let p4_r = 2 * r1 as isize - r2 as isize;
-    let p4_c = 2 * c1 as isize - c2 as isize;
+    /// Part 2: anywhere in line with the two antennas.
+    pub fn part2() -> Self {
+        Harmonics::Line
+    }
 
-    let mut positions = Vec::new();
+    /// Parses `2`, `1,2,5`, `2..5`, `1..` or `line`.
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let invalid = || format!("invalid --harmonics '{}' (e.g. 2, 1,3, 2..5, 1.. or line)", text);
+        if text == "line" {
+            return Ok(Harmonics::Line);
+        }
+        if let Some((min, max)) = text.split_once("..") {
+            let min = min.parse().map_err(|_| invalid())?;
+            let max = match max {
+                "" => None,
+                max => Some(max.parse().map_err(|_| invalid())?),
+            };
+            return Ok(Harmonics::Range { min, max });
+        }
+        text.split(',')
+            .map(|k| k.trim().parse().map_err(|_| invalid()))
+            .collect::<Result<Vec<u64>, String>>()
+            .map(Harmonics::Only)
+    }
 
-    // グリッドの範囲内かを確認
-    if p3_r >= 0 && p3_c >= 0 {
-        positions.push((p3_r as usize, p3_c as usize));
+    /// The antinodes of one antenna pair inside `bounds`.
+    pub fn antinodes<const D: usize>(&self, a: Point<D>, b: Point<D>, bounds: Point<D>) -> Vec<Point<D>> {
+        if a == b {
+            return vec![a];
+        }
+        match self {
+            Harmonics::Line => get_extended_line(a, b, bounds),
+            Harmonics::Only(multiples) => multiples
+                .iter()
+                .flat_map(|&k| [harmonic(a, b, k, bounds), harmonic(b, a, k, bounds)])
+                .flatten()
+                .collect(),
+            Harmonics::Range { min, max } => {
+                let mut points = Vec::new();
+                for k in *min..=max.unwrap_or(u64::MAX) {
+                    let pair = [harmonic(a, b, k, bounds), harmonic(b, a, k, bounds)];
+                    // Cells inside the map form one run of `k` starting at 0, so once
+                    // both sides have left the map they never come back.
+                    if pair == [None, None] {
+                        break;
+                    }
+                    points.extend(pair.into_iter().flatten());
+                }
+                points
+            }
+        }
     }
-    if p4_r >= 0 && p4_c >= 0 {
-        positions.push((p4_r as usize, p4_c as usize));
+}
+
+impl fmt::Display for Harmonics {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Harmonics::Only(multiples) => {
+                let multiples: Vec<String> = multiples.iter().map(u64::to_string).collect();
+                write!(f, "{}", multiples.join(","))
+            }
+            Harmonics::Range { min, max: Some(max) } => write!(f, "{}..{}", min, max),
+            Harmonics::Range { min, max: None } => write!(f, "{}..", min),
+            Harmonics::Line => write!(f, "line"),
+        }
     }
+}
 
-    positions
+/// `from + k * (through - from)`, if it lies inside `bounds`.
+fn harmonic<const D: usize>(from: Point<D>, through: Point<D>, k: u64, bounds: Point<D>) -> Option<Point<D>> {
+    let k = i64::try_from(k).ok()?;
+    let mut point = [0; D];
+    for axis in 0..D {
+        let delta = through[axis] as i64 - from[axis] as i64;
+        let coordinate = (from[axis] as i64).checked_add(delta.checked_mul(k)?)?;
+        point[axis] = usize::try_from(coordinate).ok().filter(|&c| c < bounds[axis])?;
+    }
+    Some(point)
 }
 
 fn is_antenna(c: char) -> bool {
@@ -38,161 +119,297 @@ fn parse_grid(input: &str) -> Vec<Vec<char>> {
         .collect()
 }
 
-fn part1(grid: &Vec<Vec<char>>) -> usize{
-    let num_rows = grid.len();
-    if num_rows == 0 {
-        println!("Empty grid.");
-        return 0;
-    }
-    let num_cols = grid[0].len();
-   // アンテナの収集と周波数によるグループ化
-   let mut freq_map: HashMap<char, Vec<(usize, usize)>> = HashMap::new();
-    
-   for (r, row) in grid.iter().enumerate() {
-       for (c, &ch) in row.iter().enumerate() {
-           if is_antenna(ch) {
-               freq_map.entry(ch).or_insert(Vec::new()).push((r, c));
-           }
-       }
-   }
-   
-   // 反節点の位置を格納するハッシュセット
-   let mut antinode_set: HashSet<(usize, usize)> = HashSet::new();
-   
-   // 各周波数グループでアンテナペアを処理
-   for (_freq, antennas) in &freq_map {
-       if antennas.len() < 2 {
-           continue; // ペアが存在しない場合はスキップ
-       }
-       for i in 0..antennas.len() {
-           for j in (i+1)..antennas.len() {
-               let p1 = antennas[i];
-               let p2 = antennas[j];
-               
-               // 反節点位置の計算
-               let positions = compute_antinode_positions(p1, p2);
-               
-               for pos in positions {
-                   // グリッドの範囲内か確認
-                   if pos.0 < num_rows && pos.1 < num_cols {
-                       antinode_set.insert(pos);
-                   }
-               }
-           }
-       }
-   }
-   
-   // ユニークな反節点の総数
-   let antinode_count = antinode_set.len();
-//    println!("Total unique antinode positions: {}", antinode_count);
-   antinode_count
-}
-
-fn part2(grid: &Vec<Vec<char>>) -> usize {
-    let num_rows = grid.len();
-    if num_rows == 0 {
-        println!("Empty grid.");
-        return 0;
-    }
-    let num_cols = grid[0].len();
-    
-    // アンテナの収集と周波数によるグループ化
-    let mut freq_map: HashMap<char, Vec<(usize, usize)>> = HashMap::new();
-    
-    for (r, row) in grid.iter().enumerate() {
-        for (c, &ch) in row.iter().enumerate() {
-            if is_antenna(ch) {
-                freq_map.entry(ch).or_insert(Vec::new()).push((r, c));
+/// Splits a voxel map into its layers, which are grids separated by blank lines.
+fn parse_layers(input: &str) -> Vec<Vec<Vec<char>>> {
+    input
+        .split("\n\n")
+        .map(parse_grid)
+        .filter(|layer| !layer.is_empty())
+        .collect()
+}
+
+/// The antennas of a map, indexed both by frequency and by cell, with the map's
+/// size. Built once from the input; antennas can then be added or removed to ask
+/// what-if questions without rereading the map.
+#[derive(Debug, Clone)]
+pub struct AntennaMap<const D: usize = 2> {
+    /// Size of the map along each axis.
+    pub bounds: Point<D>,
+    /// Antennas of each frequency, kept in reading order.
+    frequencies: BTreeMap<char, Vec<Point<D>>>,
+    cells: BTreeMap<Point<D>, char>,
+}
+
+impl<const D: usize> AntennaMap<D> {
+    pub fn new(bounds: Point<D>) -> Self {
+        AntennaMap { bounds, frequencies: BTreeMap::new(), cells: BTreeMap::new() }
+    }
+
+    /// Antennas of each frequency, in reading order.
+    pub fn frequencies(&self) -> &BTreeMap<char, Vec<Point<D>>> {
+        &self.frequencies
+    }
+
+    pub fn frequency_at(&self, point: &Point<D>) -> Option<char> {
+        self.cells.get(point).copied()
+    }
+
+    /// Places an antenna; returns false if `point` is outside the map or already taken.
+    pub fn add(&mut self, frequency: char, point: Point<D>) -> bool {
+        if (0..D).any(|axis| point[axis] >= self.bounds[axis]) || self.cells.contains_key(&point) {
+            return false;
+        }
+        self.cells.insert(point, frequency);
+        let antennas = self.frequencies.entry(frequency).or_default();
+        let index = antennas.partition_point(|&other| other < point);
+        antennas.insert(index, point);
+        true
+    }
+
+    /// Takes away the antenna at `point`, returning its frequency.
+    pub fn remove(&mut self, point: &Point<D>) -> Option<char> {
+        let frequency = self.cells.remove(point)?;
+        if let Some(antennas) = self.frequencies.get_mut(&frequency) {
+            antennas.retain(|other| other != point);
+            if antennas.is_empty() {
+                self.frequencies.remove(&frequency);
             }
         }
+        Some(frequency)
     }
-        // 反節点の位置を格納するハッシュセット
-    let mut antinode_set: HashSet<(usize, usize)> = HashSet::new();
-    
-    // 各周波数グループでアンテナペアを処理
-    for (_freq, antennas) in &freq_map {
-        if antennas.len() < 2 {
-            continue; // ペアが存在しない場合はスキップ
-        }
-        for i in 0..antennas.len() {
-            for j in (i+1)..antennas.len() {
-                let p1 = antennas[i];
-                let p2 = antennas[j];
-                
-                // 反節点位置の計算
-                let positions = get_extended_line(p1, p2, num_rows,num_cols);
-                
-                for pos in positions {
-                    // グリッドの範囲内か確認
-                    if pos.0 < num_rows && pos.1 < num_cols {
-                        antinode_set.insert(pos);
+
+    /// The antenna whose removal leaves the fewest antinodes under `harmonics`,
+    /// with that count. Ties go to the first antenna in reading order.
+    pub fn best_removal(&mut self, harmonics: &Harmonics) -> Option<(char, Point<D>, usize)> {
+        let mut best: Option<(char, Point<D>, usize)> = None;
+        let points: Vec<Point<D>> = self.cells.keys().copied().collect();
+        for point in points {
+            let frequency = self.remove(&point)?;
+            let count = attribute_antinodes(self, harmonics).count();
+            self.add(frequency, point);
+            if best.is_none_or(|(_, _, fewest)| count < fewest) {
+                best = Some((frequency, point, count));
+            }
+        }
+        best
+    }
+}
+
+impl AntennaMap<2> {
+    /// Indexes the antennas of a grid, taking the width from the first row.
+    pub fn from_grid(grid: &[Vec<char>]) -> Result<Self, String> {
+        if grid.is_empty() {
+            return Err("Empty grid.".to_string());
+        }
+        let mut map = AntennaMap::new([grid.len(), grid.first().map_or(0, Vec::len)]);
+        for (r, row) in grid.iter().enumerate() {
+            for (c, &ch) in row.iter().enumerate() {
+                if is_antenna(ch) {
+                    map.add(ch, [r, c]);
+                }
+            }
+        }
+        Ok(map)
+    }
+}
+
+impl AntennaMap<3> {
+    /// Indexes the antennas of a voxel map. Every layer must have the size of the first.
+    pub fn from_layers(layers: &[Vec<Vec<char>>]) -> Result<Self, String> {
+        let size = |layer: &Vec<Vec<char>>| [layer.len(), layer.first().map_or(0, Vec::len)];
+        let [rows, cols] = layers.first().map_or([0, 0], size);
+        if let Some((l, layer)) = layers.iter().enumerate().find(|(_, layer)| size(layer) != [rows, cols]) {
+            let [layer_rows, layer_cols] = size(layer);
+            return Err(format!(
+                "layer {} is {}x{}, but the first layer is {}x{}",
+                l + 1, layer_rows, layer_cols, rows, cols
+            ));
+        }
+        let mut map = AntennaMap::new([layers.len(), rows, cols]);
+        for (l, layer) in layers.iter().enumerate() {
+            for (r, row) in layer.iter().enumerate() {
+                for (c, &ch) in row.iter().enumerate() {
+                    if is_antenna(ch) {
+                        map.add(ch, [l, r, c]);
                     }
                 }
             }
         }
+        Ok(map)
+    }
+}
+
+/// Two antennas of one frequency whose line produced an antinode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Source<const D: usize = 2> {
+    pub frequency: char,
+    pub pair: (Point<D>, Point<D>),
+}
+
+/// How much the antinodes of different pairs and frequencies coincide.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Overlap {
+    /// Antinode cells produced by more than one antenna pair.
+    pub multi_pair: usize,
+    /// Antinode cells produced by more than one frequency.
+    pub multi_frequency: usize,
+    /// Antinode cells that sit on an antenna.
+    pub on_antenna: usize,
+    /// The most antenna pairs behind a single cell.
+    pub max_pairs: usize,
+}
+
+/// Every antinode cell with the antenna pairs that produced it.
+#[derive(Debug)]
+pub struct AntinodeReport<const D: usize = 2> {
+    pub antinodes: BTreeMap<Point<D>, Vec<Source<D>>>,
+}
+
+impl<const D: usize> AntinodeReport<D> {
+    /// Number of distinct antinode cells, the puzzle answer.
+    pub fn count(&self) -> usize {
+        self.antinodes.len()
+    }
+
+    /// Distinct antinode cells per frequency. A cell shared by two frequencies counts for both.
+    pub fn per_frequency(&self) -> BTreeMap<char, usize> {
+        let mut counts = BTreeMap::new();
+        for sources in self.antinodes.values() {
+            let mut frequencies: Vec<char> = sources.iter().map(|source| source.frequency).collect();
+            // Sources are recorded frequency by frequency, so repeats are adjacent.
+            frequencies.dedup();
+            for frequency in frequencies {
+                *counts.entry(frequency).or_insert(0) += 1;
+            }
+        }
+        counts
+    }
+
+    pub fn overlap(&self, antennas: &AntennaMap<D>) -> Overlap {
+        let mut overlap = Overlap::default();
+        for (point, sources) in &self.antinodes {
+            if sources.len() > 1 {
+                overlap.multi_pair += 1;
+            }
+            if sources.iter().any(|source| source.frequency != sources[0].frequency) {
+                overlap.multi_frequency += 1;
+            }
+            if antennas.frequency_at(point).is_some() {
+                overlap.on_antenna += 1;
+            }
+            overlap.max_pairs = overlap.max_pairs.max(sources.len());
+        }
+        overlap
+    }
+}
+
+impl AntinodeReport<2> {
+    /// The original map with every antinode drawn as `#`. Antennas stay visible,
+    /// so an antinode on an antenna only shows up in `Overlap::on_antenna`.
+    pub fn overlay(&self, grid: &[Vec<char>]) -> String {
+        draw_antinodes(grid, self.antinodes.keys().copied())
+    }
+}
+
+impl AntinodeReport<3> {
+    /// Each layer drawn like `AntinodeReport::<2>::overlay`, separated by blank lines.
+    pub fn overlay(&self, layers: &[Vec<Vec<char>>]) -> String {
+        let drawn: Vec<String> = layers
+            .iter()
+            .enumerate()
+            .map(|(l, layer)| {
+                draw_antinodes(layer, self.antinodes.keys().filter(|point| point[0] == l).map(|&[_, r, c]| [r, c]))
+            })
+            .collect();
+        drawn.join("\n")
+    }
+}
+
+fn draw_antinodes(grid: &[Vec<char>], antinodes: impl Iterator<Item = Point<2>>) -> String {
+    let mut map = grid.to_vec();
+    for [r, c] in antinodes {
+        if let Some(cell) = map[r].get_mut(c).filter(|cell| !is_antenna(**cell)) {
+            *cell = '#';
+        }
+    }
+    map.iter().map(|row| row.iter().collect::<String>() + "\n").collect()
+}
+
+/// Applies `harmonics` to every pair of same-frequency antennas and records which
+/// pairs produced each cell inside `bounds`.
+fn attribute_antinodes<const D: usize>(
+    antennas: &AntennaMap<D>,
+    harmonics: &Harmonics,
+) -> AntinodeReport<D> {
+    let mut report = AntinodeReport { antinodes: BTreeMap::new() };
+
+    // 各周波数グループでアンテナペアを処理
+    for (&frequency, group) in antennas.frequencies() {
+        for (i, &p1) in group.iter().enumerate() {
+            for &p2 in &group[i + 1..] {
+                for pos in harmonics.antinodes(p1, p2, antennas.bounds) {
+                    report.antinodes.entry(pos).or_default().push(Source { frequency, pair: (p1, p2) });
+                }
+            }
+        }
+    }
+    report
+}
+
+fn part1<const D: usize>(antennas: &AntennaMap<D>) -> AntinodeReport<D> {
+    attribute_antinodes(antennas, &Harmonics::part1())
+}
+
+fn part2<const D: usize>(antennas: &AntennaMap<D>) -> AntinodeReport<D> {
+    attribute_antinodes(antennas, &Harmonics::part2())
+}
+
+/// 直線上のすべてのグリッド位置を求める関数（Bresenham's Algorithmを拡張）
+/// Works in any number of dimensions: the step is the difference divided by the
+/// gcd of all its components.
+fn get_extended_line<const D: usize>(start: Point<D>, end: Point<D>, bounds: Point<D>) -> Vec<Point<D>> {
+    let from = start.map(|v| v as isize);
+    let mut delta = [0isize; D];
+    for axis in 0..D {
+        delta[axis] = end[axis] as isize - from[axis];
     }
-    // ユニークな反節点の総数
-    let antinode_count = antinode_set.len();
-    // println!("Total unique antinode positions: {}", antinode_count);
-    // オプション: 反節点の座標を表示する場合
-    
-    // println!("Antinode Positions:");
-    // for (r, c) in &antinode_set {
-    //     println!("({}, {})", r, c);
-    // }
-    
-    antinode_count
-}
-
-/// 2点間の直線上のすべてのグリッド位置を求める関数（Bresenham's Algorithmを拡張）
-fn get_extended_line(start: (usize, usize), end: (usize, usize), num_rows: usize, num_cols: usize) -> Vec<(usize, usize)> {
-    let (y0, x0) = (start.0 as isize, start.1 as isize); // y=row, x=col
-    let (y1, x1) = (end.0 as isize, end.1 as isize);
-    
-    let delta_x = x1 - x0;
-    let delta_y = y1 - y0;
-    
+
     // gcd計算をして歩幅を決定
-    let step_gcd = gcd(delta_x.abs(), delta_y.abs());
-    
+    let step_gcd = delta.iter().fold(0, |acc, d| gcd(acc, d.abs()));
+
     if step_gcd == 0 {
         return vec![start];
     }
-    
-    let step_x = if delta_x == 0 { 0 } else { delta_x / step_gcd };
-    let step_y = if delta_y == 0 { 0 } else { delta_y / step_gcd };
-    
+
+    let step = delta.map(|d| d / step_gcd);
+    let inside = |point: &[isize; D]| (0..D).all(|axis| point[axis] >= 0 && point[axis] < bounds[axis] as isize);
+    let advance = |point: &[isize; D], sign: isize| {
+        let mut next = *point;
+        for axis in 0..D {
+            next[axis] += sign * step[axis];
+        }
+        next
+    };
+
     // 反対方向に歩くことでラインをグリッドの境界まで拡張
-    let mut x = x0;
-    let mut y = y0;
-    
+    let mut point = from;
+
     // バックワード
     loop {
-        let next_x = x - step_x;
-        let next_y = y - step_y;
-        if next_x < 0 || next_x >= num_cols as isize || next_y < 0 || next_y >= num_rows as isize {
+        let next = advance(&point, -1);
+        if !inside(&next) {
             break;
         }
-        x = next_x;
-        y = next_y;
+        point = next;
     }
-    
+
     // 前方に歩きながらポイントを収集
     let mut points = Vec::new();
-    loop {
-        if x >= 0 && x < num_cols as isize && y >= 0 && y < num_rows as isize {
-            points.push((y as usize, x as usize));
-        } else {
-            break;
-        }
-        x += step_x;
-        y += step_y;
-        // 無限ループを防ぐためにステップがない場合は終了
-        if step_x == 0 && step_y == 0 {
-            break;
-        }
+    while inside(&point) {
+        points.push(point.map(|v| v as usize));
+        point = advance(&point, 1);
     }
-    
+
     points
 }
 
@@ -201,13 +418,107 @@ fn gcd(a: isize, b: isize) -> isize {
     if b == 0 { a } else { gcd(b, a % b) }
 }
 
+fn format_point<const D: usize>(point: &Point<D>) -> String {
+    let coordinates: Vec<String> = point.iter().map(usize::to_string).collect();
+    format!("({})", coordinates.join(","))
+}
+
+/// Prints per-frequency counts, overlap statistics and the sources of every antinode.
+fn print_attribution<const D: usize>(
+    name: &str,
+    report: &AntinodeReport<D>,
+    antennas: &AntennaMap<D>,
+) {
+    println!("{} antinodes by frequency:", name);
+    for (frequency, count) in report.per_frequency() {
+        println!("  {}: {}", frequency, count);
+    }
+    let overlap = report.overlap(antennas);
+    println!(
+        "  shared by several pairs: {}, by several frequencies: {}, on an antenna: {}, most pairs on one cell: {}",
+        overlap.multi_pair, overlap.multi_frequency, overlap.on_antenna, overlap.max_pairs
+    );
+    for (point, sources) in &report.antinodes {
+        let pairs: Vec<String> = sources
+            .iter()
+            .map(|source| format!("{} {}-{}", source.frequency, format_point(&source.pair.0), format_point(&source.pair.1)))
+            .collect();
+        println!("  {}: {}", format_point(point), pairs.join(", "));
+    }
+}
+
+/// Prints both parts, plus the `--harmonics` count if given, then any reports asked for.
+/// `--best-removal` asks which single antenna to take away for the fewest antinodes.
+fn report<const D: usize>(
+    antennas: &AntennaMap<D>,
+    harmonics: Option<&Harmonics>,
+    overlay: impl Fn(&AntinodeReport<D>) -> String,
+) {
+    let part1 = part1(antennas);
+    let part2 = part2(antennas);
+    println!("Part1:  {:?}", part1.count());
+    println!("Part2:  {:?}", part2.count());
+
+    let mut reports = vec![
+        ("Part1".to_string(), Harmonics::part1(), part1),
+        ("Part2".to_string(), Harmonics::part2(), part2),
+    ];
+    if let Some(harmonics) = harmonics {
+        let report = attribute_antinodes(antennas, harmonics);
+        println!("Harmonics {}:  {}", harmonics, report.count());
+        reports.push((format!("Harmonics {}", harmonics), harmonics.clone(), report));
+    }
+    for (name, harmonics, report) in &reports {
+        if utils::has_flag("--best-removal") {
+            match antennas.clone().best_removal(harmonics) {
+                Some((frequency, point, count)) => println!(
+                    "{} best removal: {} at {} leaves {} of {} antinodes",
+                    name, frequency, format_point(&point), count, report.count()
+                ),
+                None => println!("{} best removal: no antennas", name),
+            }
+        }
+        if utils::has_flag("--attribution") {
+            print_attribution(name, report, antennas);
+        }
+        if utils::has_flag("--overlay") {
+            println!("{} antinodes:\n{}", name, overlay(report));
+        }
+    }
+}
+
 pub fn solve(day: u32) {
     println!("Started Day{}!",day );
-    if let Ok(contents) =  utils::read_file( &format!("src/solutions/day{}/input.txt", day)) {
+    let harmonics = match utils::flag_value("--harmonics").map(|text| Harmonics::parse(&text)) {
+        None => None,
+        Some(Ok(harmonics)) => Some(harmonics),
+        Some(Err(e)) => {
+            println!("{}", e);
+            return;
+        }
+    };
+    let path = utils::flag_value("--map").unwrap_or(format!("src/solutions/day{}/input.txt", day));
+    if let Ok(contents) =  utils::read_file(&path) {
+        // Several blank-line separated grids are the layers of a voxel map.
+        let layers = parse_layers(&contents);
+        if layers.len() > 1 {
+            let antennas = match AntennaMap::from_layers(&layers) {
+                Ok(antennas) => antennas,
+                Err(e) => {
+                    println!("{}", e);
+                    return;
+                }
+            };
+            let [depth, rows, cols] = antennas.bounds;
+            println!("Voxel map: {} layers of {}x{}", depth, rows, cols);
+            report(&antennas, harmonics.as_ref(), |report| report.overlay(&layers));
+            return;
+        }
+
         let grid =  parse_grid(&contents);
-        let part1 = part1(&grid) ; 
-        let part2 = part2(&grid);
-        println!("Part1:  {:?}", part1);
-        println!("Part2:  {:?}", part2);
+        match AntennaMap::from_grid(&grid) {
+            Ok(antennas) => report(&antennas, harmonics.as_ref(), |report| report.overlay(&grid)),
+            Err(e) => println!("{}", e),
+        }
     }
-}
\ No newline at end of file
+}
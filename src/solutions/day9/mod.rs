@@ -1,61 +1,81 @@
 use crate::utils;
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BinaryHeap};
+use std::fmt;
 
-/// ディスクを `Option<u128>` のベクターとして表現
-/// `None` は空きスペース（`.`）、`Some(id)` はファイルID
-type Disk = Vec<Option<u128>>;
+/// ディスク上の連続した領域（開始位置と長さ）
+#[derive(Clone, Copy, Debug)]
+struct Span {
+    start: usize,
+    len: usize,
+}
+
+impl Span {
+    fn end(&self) -> usize {
+        self.start + self.len
+    }
+}
+
+/// ディスクをファイル領域と空き領域の run-length 表現で保持する
+/// `files[id]` はファイルIDが `id` のファイル、`free[i]` はファイル `i` の直後の空き領域
+#[derive(Debug)]
+struct Disk {
+    files: Vec<Span>,
+    free: Vec<Span>,
+}
+
+/// コンパクション後にファイル（またはその一部）が置かれた領域
+#[derive(Clone, Copy, Debug)]
+struct Extent {
+    id: u128,
+    span: Span,
+}
 
 /// ディスクマップ文字列を解析し、ディスクの表現を作成する関数
 /// ファイルと空きスペースを交互に処理し、ファイルIDを0から順に割り当てる
 fn parse_disk_map(disk_map: &str) -> Disk {
-    let mut disk: Disk = Vec::new();
-    let mut chars = disk_map.chars();
+    let mut disk = Disk { files: Vec::new(), free: Vec::new() };
     let mut is_file = true; // 最初はファイル
-    let mut file_id: u128 = 0; // ファイルIDは0から開始
+    let mut position = 0;
 
-    while let Some(c) = chars.next() {
-        if !c.is_digit(10) {
-            continue; // 数字以外はスキップ
-        }
-        let length = c.to_digit(10).unwrap() as usize;
+    for length in disk_map.chars().filter_map(|c| c.to_digit(10)) {
+        let span = Span { start: position, len: length as usize };
         if is_file {
-            for _ in 0..length {
-                disk.push(Some(file_id));
-            }
-            file_id += 1;
+            disk.files.push(span);
         } else {
-            for _ in 0..length {
-                disk.push(None); // 空きスペース
-            }
+            disk.free.push(span); // 空きスペース
         }
+        position = span.end();
         is_file = !is_file; // ファイルと空きスペースを交互に
     }
 
     disk
 }
 
-/// ディスクを視覚的に表示する関数
-/// 空きスペースは '.'、ファイルブロックはファイルIDを表示
-// fn display_disk(disk: &Disk) -> String {
-//     disk.iter()
-//         .map(|block| match block {
-//             Some(id) => id.to_string(),
-//             None => ".".to_string(),
-//         })
-//         .collect::<Vec<String>>()
-//         .join("")
+// /// ディスクを視覚的に表示する関数
+// /// 空きスペースは '.'、ファイルブロックはファイルIDを表示
+// fn display_disk(extents: &[Extent]) -> String {
+//     let size = extents.iter().map(|e| e.span.end()).max().unwrap_or(0);
+//     let mut blocks = vec![".".to_string(); size];
+//     for extent in extents {
+//         for pos in extent.span.start..extent.span.end() {
+//             blocks[pos] = extent.id.to_string();
+//         }
+//     }
+//     blocks.join("")
 // }
 
 /// チェックサムを計算する関数
-/// 各ファイルブロックの位置とファイルIDの積を合計
-fn calculate_checksum(disk: &Disk) -> u128 {
-    disk.iter()
-        .enumerate()
-        .filter_map(|(pos, &block)| {
-            if let Some(id) = block {
-                Some(pos as u128 * id)
-            } else {
-                None
-            }
+/// 各ファイルブロックの位置とファイルIDの積を合計（領域ごとに等差数列の和で求める）
+fn calculate_checksum(extents: &[Extent]) -> u128 {
+    extents
+        .iter()
+        .map(|extent| {
+            let start = extent.span.start as u128;
+            let len = extent.span.len as u128;
+            // start + (start + 1) + ... + (start + len - 1)
+            let positions = len * start + len * len.saturating_sub(1) / 2;
+            extent.id * positions
         })
         .sum()
 }
@@ -64,14 +84,13 @@ fn calculate_checksum(disk: &Disk) -> u128 {
 fn part1(contents: &str) -> u128 {
     // ディスクマップを解析
     let parsed_disk = parse_disk_map(contents);
-    // println!("Initial Disk (Part1): {}", display_disk(&parsed_disk));
 
     // コンパクト（ブロック単位の移動）
-    let compacted_disk = compact_disk_step_by_step(parsed_disk);
-    // println!("Compacted Disk (Part1): {}", display_disk(&compacted_disk));
+    let compacted = compact(&parsed_disk, Strategy::BlockWise);
+    // println!("Compacted Disk (Part1): {}", display_disk(&compacted));
 
     // チェックサムを計算
-    let checksum = calculate_checksum(&compacted_disk);
+    let checksum = calculate_checksum(&compacted);
     println!("Filesystem Checksum (Part1): {}", checksum);
     checksum
 }
@@ -80,150 +99,286 @@ fn part1(contents: &str) -> u128 {
 fn part2(contents: &str) -> u128 {
     // ディスクマップを解析
     let parsed_disk = parse_disk_map(contents);
-    // println!("Initial Disk (Part2): {}", display_disk(&parsed_disk));
 
     // コンパクト（ファイル単位の移動）
-    let compacted_disk = compact_disk_move_files_left(parsed_disk);
-    // println!("Compacted Disk (Part2): {}", display_disk(&compacted_disk));
+    let compacted = compact(&parsed_disk, Strategy::FirstFit);
+    // println!("Compacted Disk (Part2): {}", display_disk(&compacted));
 
     // チェックサムを計算
-    let checksum = calculate_checksum(&compacted_disk);
+    let checksum = calculate_checksum(&compacted);
     println!("Filesystem Checksum (Part2): {}", checksum);
     checksum
 }
 
 /// パート1: 各ブロックを左端の適切な空きスペースに一つずつ移動するコンパクション
-fn compact_disk_step_by_step(mut disk: Disk) -> Disk {
-    loop {
-        // 左から最初の空きスペースを見つける
-        let first_free = disk.iter().position(|&c| c.is_none());
-
-        match first_free {
-            Some(pos_free) => {
-                // 空きスペースの右側で最も右にあるファイルブロックを見つける
-                let file_pos = if pos_free + 1 < disk.len() {
-                    disk[pos_free + 1..]
-                        .iter()
-                        .rposition(|&c| c.is_some()) // 右から最初のファイルブロック
-                        .map(|pos| pos_free + 1 + pos)
-                } else {
-                    None
-                };
-
-                match file_pos {
-                    Some(pos_file) => {
-                        // ファイルブロックを空きスペースに移動
-                        disk[pos_free] = disk[pos_file];
-                        disk[pos_file] = None;
-                    }
-                    None => {
-                        // 空きスペースの右側にファイルブロックが無い場合、完了
-                        break;
-                    }
+/// 左の空き領域と右端のファイルを二つのポインタで進め、各領域を一度だけ走査する
+fn compact_disk_step_by_step(disk: &Disk) -> Vec<Extent> {
+    let mut extents = Vec::new();
+    // まだブロックが残っている最も右のファイルと、その残りブロック数
+    let mut back = disk.files.len();
+    let mut remaining = 0;
+
+    'gaps: for (gap_index, gap) in disk.free.iter().enumerate() {
+        // 残りブロックのあるファイルがこの空き領域より左にあれば完了
+        if back <= gap_index {
+            break;
+        }
+        let mut position = gap.start;
+        while position < gap.end() {
+            if remaining == 0 {
+                // 空き領域より右にファイルが無ければ完了
+                if back <= gap_index + 1 {
+                    break 'gaps;
                 }
+                back -= 1;
+                remaining = disk.files[back].len;
+                continue;
             }
-            None => {
-                // 空きスペースが無い場合、完了
-                break;
-            }
+            // 右端のファイルの末尾ブロックから空き領域を埋める
+            let take = remaining.min(gap.end() - position);
+            extents.push(Extent { id: back as u128, span: Span { start: position, len: take } });
+            position += take;
+            remaining -= take;
         }
     }
-    disk
+
+    // 移動されなかったファイルは元の位置に残る（途中まで移動したファイルは先頭側が残る）
+    let unmoved = disk.files.iter().take(back).enumerate().map(|(id, &span)| Extent { id: id as u128, span });
+    extents.extend(unmoved);
+    if let Some(&span) = disk.files.get(back) {
+        extents.push(Extent { id: back as u128, span: Span { start: span.start, len: remaining } });
+    }
+    extents
 }
 
-fn compact_disk_move_files_left(mut disk: Disk) -> Disk {
-    // ファイルのリストを取得
-    let mut files = list_files(&disk);
-
-    // ファイルIDが高い順にソート
-    files.sort_by(|a, b| b.0.cmp(&a.0));
-
-    for (file_id, start, end) in files.iter() {
-        let size = end - start;
-
-        // 移動先の空きスペースを探す
-        if let Some(target_pos) = find_leftmost_free_span(&disk, size) {
-            if target_pos < *start {
-                // 移動先の空きスペースにファイルが完全にフィットするか確認
-                let can_fit = disk[target_pos..target_pos + size]
-                    .iter()
-                    .all(|&block| block.is_none());
-
-                if can_fit {
-                    // ファイルを移動
-                    for pos in *start..*end {
-                        disk[pos] = None;
-                    }
-                    for pos in 0..size {
-                        disk[target_pos + pos] = Some(*file_id);
-                    }
-                }
-                // 移動できない場合はそのまま
-            }
-            // 移動先が元の位置より右側の場合は移動しない
+/// コンパクションの空き領域の選び方
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Strategy {
+    /// ファイル全体が収まる最も左の空き領域（パート2）
+    FirstFit,
+    /// ファイル全体が収まる最も小さい空き領域（同じサイズなら左優先）
+    BestFit,
+    /// ファイル全体が収まる最も大きい空き領域（同じサイズなら左優先）
+    WorstFit,
+    /// 前回割り当てた位置から右へ探し、見つからなければ先頭から探す
+    NextFit,
+    /// ブロック単位で左端の空きへ移動する（パート1）
+    BlockWise,
+}
+
+impl Strategy {
+    const ALL: [Strategy; 5] = [
+        Strategy::FirstFit,
+        Strategy::BestFit,
+        Strategy::WorstFit,
+        Strategy::NextFit,
+        Strategy::BlockWise,
+    ];
+
+    fn parse(name: &str) -> Option<Self> {
+        match name {
+            "first-fit" | "first" => Some(Strategy::FirstFit),
+            "best-fit" | "best" => Some(Strategy::BestFit),
+            "worst-fit" | "worst" => Some(Strategy::WorstFit),
+            "next-fit" | "next" => Some(Strategy::NextFit),
+            "block-wise" | "blocks" => Some(Strategy::BlockWise),
+            _ => None,
         }
-        // 移動先の空きスペースがない場合はそのまま
     }
+}
 
-    disk
+impl fmt::Display for Strategy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Strategy::FirstFit => write!(f, "first-fit"),
+            Strategy::BestFit => write!(f, "best-fit"),
+            Strategy::WorstFit => write!(f, "worst-fit"),
+            Strategy::NextFit => write!(f, "next-fit"),
+            Strategy::BlockWise => write!(f, "block-wise"),
+        }
+    }
+}
+
+/// 指定した方式でディスクをコンパクトにする
+fn compact(disk: &Disk, strategy: Strategy) -> Vec<Extent> {
+    match strategy {
+        Strategy::BlockWise => compact_disk_step_by_step(disk),
+        _ => compact_disk_move_files_left(disk, strategy),
+    }
 }
 
+/// ファイル単位の移動で使う空き領域の管理
+enum FreeSpace {
+    /// サイズごとの最小ヒープ（開始位置順）: first-fit / best-fit / worst-fit 用
+    BySize { strategy: Strategy, heaps: Vec<BinaryHeap<Reverse<usize>>> },
+    /// 開始位置順の空き領域と前回の割り当て位置: next-fit 用
+    Roving { gaps: BTreeMap<usize, usize>, cursor: usize },
+}
+
+impl FreeSpace {
+    fn new(strategy: Strategy, gaps: &[Span]) -> Self {
+        if strategy == Strategy::NextFit {
+            let gaps = gaps.iter().map(|gap| (gap.start, gap.len)).collect();
+            return FreeSpace::Roving { gaps, cursor: 0 };
+        }
+        let max_len = gaps.iter().map(|gap| gap.len).max().unwrap_or(0);
+        let mut heaps: Vec<BinaryHeap<Reverse<usize>>> = vec![BinaryHeap::new(); max_len + 1];
+        for gap in gaps {
+            heaps[gap.len].push(Reverse(gap.start));
+        }
+        FreeSpace::BySize { strategy, heaps }
+    }
 
-/// ファイルを特定し、ファイルIDとサイズをリスト化する関数
-/// 戻り値は (file_id, start_pos, end_pos) のベクター
-fn list_files(disk: &Disk) -> Vec<(u128, usize, usize)> {
-    let mut files = Vec::new();
-    let mut current_id: Option<u128> = None;
-    let mut start_pos = 0;
-
-    for (pos, &block) in disk.iter().enumerate() {
-        match block {
-            Some(id) => {
-                if Some(id) != current_id {
-                    // 新しいファイルの開始
-                    if let Some(prev_id) = current_id {
-                        // 前のファイルを保存
-                        files.push((prev_id, start_pos, pos));
-                    }
-                    current_id = Some(id);
-                    start_pos = pos;
+    /// `file` 全体が収まり `file.start` より左にある空き領域を割り当て、その開始位置を返す
+    /// 長さ0のファイルは移動しない
+    fn allocate(&mut self, file: Span) -> Option<usize> {
+        if file.len == 0 {
+            return None;
+        }
+        match self {
+            FreeSpace::BySize { strategy, heaps } => {
+                // 各サイズのヒープの先頭がそのサイズで最も左の空き領域
+                let candidates = (file.len..heaps.len())
+                    .filter_map(|size| heaps[size].peek().map(|&Reverse(start)| (start, size)))
+                    .filter(|&(start, _)| start < file.start);
+                let (start, size) = match strategy {
+                    Strategy::BestFit => candidates.min_by_key(|&(start, size)| (size, start)),
+                    Strategy::WorstFit => candidates.min_by_key(|&(start, size)| (Reverse(size), start)),
+                    _ => candidates.min(),
+                }?;
+                heaps[size].pop();
+                // 残った空き領域を対応するサイズのヒープに戻す
+                if size > file.len {
+                    heaps[size - file.len].push(Reverse(start + file.len));
                 }
+                Some(start)
             }
-            None => {
-                if let Some(prev_id) = current_id {
-                    // 現在のファイルを終了
-                    files.push((prev_id, start_pos, pos));
-                    current_id = None;
+            FreeSpace::Roving { gaps, cursor } => {
+                let fits = |(&start, &len): (&usize, &usize)| (len >= file.len).then_some((start, len));
+                let wrap = (*cursor).min(file.start);
+                let (start, len) = gaps
+                    .range(*cursor..file.start.max(*cursor))
+                    .find_map(fits)
+                    .or_else(|| gaps.range(..wrap).find_map(fits))?;
+                gaps.remove(&start);
+                if len > file.len {
+                    gaps.insert(start + file.len, len - file.len);
                 }
+                *cursor = start + file.len;
+                Some(start)
             }
         }
     }
+}
 
-    // 最後のファイルが終了していない場合
-    if let Some(prev_id) = current_id {
-        files.push((prev_id, start_pos, disk.len()));
+/// ファイルIDが高い順に、ファイル全体を `strategy` で選んだ左側の空きスペースへ移動する
+/// 移動元に空いた領域は以降のファイル（すべてより左にある）の移動先にならないため戻さない
+fn compact_disk_move_files_left(disk: &Disk, strategy: Strategy) -> Vec<Extent> {
+    // 長さ0のファイルを挟んで隣接する空き領域は一つの連続した空きとして扱う
+    let mut gaps: Vec<Span> = Vec::with_capacity(disk.free.len());
+    for &gap in disk.free.iter().filter(|gap| gap.len > 0) {
+        match gaps.last_mut() {
+            Some(last) if last.end() == gap.start => last.len += gap.len,
+            _ => gaps.push(gap),
+        }
     }
 
-    // 各ファイルのサイズを加えたリストを生成
-    files.iter().map(|&(id, start, end)| (id, start, end)).collect()
+    let mut free = FreeSpace::new(strategy, &gaps);
+    let mut extents = Vec::with_capacity(disk.files.len());
+    for (id, &file) in disk.files.iter().enumerate().rev() {
+        // 移動先が無い場合はそのまま
+        let span = match free.allocate(file) {
+            Some(start) => Span { start, len: file.len },
+            None => file,
+        };
+        extents.push(Extent { id: id as u128, span });
+    }
+
+    extents
 }
 
-/// 最も左にある適切な空きスペースを探す関数
-fn find_leftmost_free_span(disk: &Disk, size: usize) -> Option<usize> {
-    let mut consecutive = 0;
-    for (pos, &block) in disk.iter().enumerate() {
-        if block.is_none() {
-            consecutive += 1;
-            if consecutive == size {
-                return Some(pos + 1 - size);
-            }
+/// コンパクション後のディスクの断片化などの指標
+#[derive(Debug)]
+struct Metrics {
+    free_spans: usize,
+    largest_free: usize,
+    free_blocks: usize,
+    moved: usize,
+    in_place: usize,
+    checksum: u128,
+}
+
+impl Metrics {
+    /// 断片化率: 1 - 最大の空き領域 / 空きブロックの総数（空きが無ければ0）
+    fn fragmentation(&self) -> f64 {
+        if self.free_blocks == 0 {
+            0.0
         } else {
-            consecutive = 0;
+            1.0 - self.largest_free as f64 / self.free_blocks as f64
         }
     }
-    None
 }
 
+impl fmt::Display for Metrics {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "checksum {}, files moved {} / in place {}, free spans {} (largest {}, fragmentation {:.3})",
+            self.checksum,
+            self.moved,
+            self.in_place,
+            self.free_spans,
+            self.largest_free,
+            self.fragmentation()
+        )
+    }
+}
+
+/// コンパクション結果の指標を求める関数
+/// 空き領域はディスク全体（元の長さ）の中でファイルに使われていない連続ブロック
+fn measure(disk: &Disk, extents: &[Extent]) -> Metrics {
+    let size = disk.files.iter().chain(&disk.free).map(Span::end).max().unwrap_or(0);
+    let mut used: Vec<Span> = extents.iter().map(|extent| extent.span).filter(|span| span.len > 0).collect();
+    used.sort_by_key(|span| span.start);
+
+    let mut metrics = Metrics {
+        free_spans: 0,
+        largest_free: 0,
+        free_blocks: 0,
+        moved: 0,
+        in_place: 0,
+        checksum: calculate_checksum(extents),
+    };
+    let mut position = 0;
+    for span in used.iter().chain(std::iter::once(&Span { start: size, len: 0 })) {
+        if span.start > position {
+            let len = span.start - position;
+            metrics.free_spans += 1;
+            metrics.free_blocks += len;
+            metrics.largest_free = metrics.largest_free.max(len);
+        }
+        position = position.max(span.end());
+    }
+
+    // 元の領域のまま一つの領域に収まっているファイルを「移動なし」とする
+    let mut pieces: BTreeMap<u128, Vec<Span>> = BTreeMap::new();
+    for extent in extents.iter().filter(|extent| extent.span.len > 0) {
+        pieces.entry(extent.id).or_default().push(extent.span);
+    }
+    for (id, file) in disk.files.iter().enumerate() {
+        let in_place = match pieces.get(&(id as u128)).map(Vec::as_slice) {
+            None => true,
+            Some([span]) => span.start == file.start && span.len == file.len,
+            Some(_) => false,
+        };
+        if in_place {
+            metrics.in_place += 1;
+        } else {
+            metrics.moved += 1;
+        }
+    }
+    metrics
+}
 
 /// ソルバーメイン関数
 pub fn solve(day: u32) {
@@ -236,5 +391,25 @@ pub fn solve(day: u32) {
         // パート2の処理
         let part2 = part2(&contents);
         println!("Part2 Checksum: {:?}", part2);
+
+        // 指定した方式（`all` ならすべて）でのコンパクション結果の指標
+        if let Some(name) = utils::flag_value("--strategy") {
+            let strategies = match Strategy::parse(&name) {
+                Some(strategy) => vec![strategy],
+                None if name == "all" => Strategy::ALL.to_vec(),
+                None => {
+                    println!(
+                        "invalid --strategy '{}' (first-fit, best-fit, worst-fit, next-fit, block-wise or all)",
+                        name
+                    );
+                    return;
+                }
+            };
+            let disk = parse_disk_map(&contents);
+            for strategy in strategies {
+                let metrics = measure(&disk, &compact(&disk, strategy));
+                println!("{}: {}", strategy, metrics);
+            }
+        }
     }
-}
\ No newline at end of file
+}
@@ -5,11 +5,28 @@ use std::fs;
 pub fn read_file(path: &str) -> Result<String, std::io::Error> {
     fs::read_to_string(path)
 }
+
+/// Returns true if `name` (e.g. `--stats`) was passed on the command line.
+pub fn has_flag(name: &str) -> bool {
+    std::env::args().any(|arg| arg == name)
+}
+
+/// Returns the value following `name`, accepting both `--name value` and `--name=value`.
+pub fn flag_value(name: &str) -> Option<String> {
+    let mut args = std::env::args();
+    while let Some(arg) = args.next() {
+        if arg == name {
+            return args.next();
+        }
+        if let Some(value) = arg.strip_prefix(name).and_then(|rest| rest.strip_prefix('=')) {
+            return Some(value.to_string());
+        }
+    }
+    None
+}
 // fn parse_grid(input: &str) -> Grid {
 //     input
 //         .lines()
 //         .map(|line| line.chars().collect())
 //         .collect()
 // }
-
-